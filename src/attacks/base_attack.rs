@@ -1,17 +1,14 @@
 use bevy::prelude::*;
 use crate::components::humanoid::{ActionState, VisualEntity, VisualRef};
 use crate::components::island::OnIsland;
-use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, AttackSpec, DamageEvent, Interruptable};
+use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, DamageEvent, Interruptable};
 use crate::preludes::humanoid_preludes::*;
-use crate::components::enemy::STANDARD;
-
-const DAMAGE: u64 = 10;
 
 #[derive(Component)]
 #[require(Interruptable)]
 pub struct BaseAttack {
     direction: IVec3,
-    timer: Timer,
+    elapsed: f32,
     hit: bool
 }
 
@@ -19,7 +16,7 @@ impl Default for BaseAttack {
     fn default() -> Self {
         BaseAttack { 
             direction: IVec3::X, 
-            timer: Timer::from_seconds(0.1, TimerMode::Once),
+            elapsed: 0.0,
             hit: false
         }
     }
@@ -36,48 +33,51 @@ impl Plugin for BaseAttackPlugin {
 
 fn register_base_attack(
     mut registry: ResMut<AttackRegistry>,
-    mut catalog: ResMut<AttackCatalogue>,
 ) {
     registry.register::<BaseAttack>(|commands, entity, offset| {
         if let Ok(mut ec) = commands.get_entity(entity) {
             ec.insert(BaseAttack {
                 direction: offset,
-                timer: Timer::from_seconds(0.20, TimerMode::Once),
-                hit: false,
+                ..default()
             });
         } 
     });
-    let key = key_of::<BaseAttack>();
-    catalog.0.insert(key, AttackSpec {offsets: &STANDARD, cooldown: 0.4, damage: DAMAGE });
 }
 
 fn perform_attack(
     time: Res<Time>,
     mut commands: Commands,
+    catalogue: Res<AttackCatalogue>,
     mut attacks: Query<(Entity, &ChildOf, &mut BaseAttack)>,
     mut parent_query: Query<(&Position, &mut ActionState, &OnIsland)>,
     visual_query: Query<&VisualRef>,
     mut transform_query: Query<(&mut Transform, &GlobalTransform), With<VisualEntity>>
 ) {
+    let attack_id = key_of::<BaseAttack>();
+    let Some(spec) = catalogue.0.get(&attack_id) else { return };
+
     for (child_entity, parent, mut attack) in &mut attacks {
         let mut t = 0.0;
         if let Ok((pos, mut state, island)) = parent_query.get_mut(parent.0) { //probably missing visual, globaltransform, or something when server is not on island...
             //logic
             *state = ActionState::Attacking;
-            attack.timer.tick(time.delta());
+            attack.elapsed += time.delta_secs();
 
-            t = (attack.timer.elapsed_secs() / attack.timer.duration().as_secs_f32()).clamp(0.0, 1.0);
-            if !attack.hit && t >= 0.5 {
+            t = (attack.elapsed / spec.duration().max(f32::EPSILON)).clamp(0.0, 1.0);
+            if !attack.hit && attack.elapsed >= spec.windup {
                 attack.hit = true;
-                commands.trigger(DamageEvent::new(
-                    parent.0,
-                    island.0,
-                    pos.0 + attack.direction,
-                    DAMAGE
-                ));
+                for reach in 1..=spec.range as i32 {
+                    commands.trigger(DamageEvent::new(
+                        parent.0,
+                        island.0,
+                        pos.0 + attack.direction * reach,
+                        spec.damage,
+                        attack_id
+                    ));
+                }
             }
 
-            if attack.timer.finished() {
+            if attack.elapsed >= spec.duration() {
                 commands.entity(child_entity).despawn();
                 *state = ActionState::Idle;
             }
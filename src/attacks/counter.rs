@@ -1,18 +1,13 @@
 use bevy::prelude::*;
-use crate::components::humanoid::{ActionState, Stunned};
-use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, AttackSpec, NegatingDamage, NegatedDamageEvent};
+use crate::components::humanoid::ActionState;
+use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, NegatingDamage, NegatedDamageEvent};
 use crate::preludes::humanoid_preludes::*;
-use crate::components::enemy::STANDARD;
-
-const DAMAGE: u64 = 10;
-const ATTACK_LENGTH: f32 = 5.0;
-const COOLDOWN: f32 = 6.0;
 
 #[derive(Component)]
 #[require(NegatingDamage(key_of::<Counter>()))]
 pub struct Counter {
     direction: IVec3,
-    timer: Timer,
+    elapsed: f32,
     hit: bool
 }
 
@@ -20,7 +15,7 @@ impl Default for Counter {
     fn default() -> Self {
         Counter { 
             direction: IVec3::X, 
-            timer: Timer::from_seconds(0.1, TimerMode::Once),
+            elapsed: 0.0,
             hit: false
         }
     }
@@ -35,64 +30,81 @@ impl Plugin for CounterPlugin {
     }
 }
 
+pub struct CounterPresentationPlugin;
+impl Plugin for CounterPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, visualize_counter);
+    }
+}
+
 fn register_base_attack(
     mut registry: ResMut<AttackRegistry>,
-    mut catalog: ResMut<AttackCatalogue>,
 ) {
     registry.register::<Counter>(|commands, entity, offset| {
         if let Ok(mut ec) = commands.get_entity(entity) {
             ec.insert(Counter {
                 direction: offset,
-                timer: Timer::from_seconds(ATTACK_LENGTH, TimerMode::Once),
-                hit: false,
+                ..default()
             });
         } 
     });
-    let key = key_of::<Counter>();
-    catalog.0.insert(key, AttackSpec {offsets: &STANDARD, cooldown: COOLDOWN, damage: DAMAGE });
 }
 
+// The countered attacker gets the counter's on-hit effects
 fn process_counter(
     mut reader: EventReader<NegatedDamageEvent>,
     counter_query: Query<Entity, With<Counter>>,
+    catalogue: Res<AttackCatalogue>,
     mut commands: Commands,
 ) {
     for event in reader.read() {
         if let Ok(entity) = counter_query.get(event.victim) {
-            commands.entity(event.owner).insert(Stunned::new(10.0));
+            if let Some(spec) = catalogue.0.get(&key_of::<Counter>()) {
+                for effect in spec.on_hit.iter() {
+                    effect.apply(&mut commands, event.owner);
+                }
+            }
             commands.entity(entity).despawn();
         }
     }
 }
 
+fn visualize_counter(
+    mut commands: Commands,
+    attacks: Query<Entity, Added<Counter>>,
+    mut meshes: ResMut<Assets<Mesh>>, 
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for child_entity in &attacks {
+        commands.entity(child_entity).insert((
+            Mesh3d(meshes.add(Cuboid::new(1.1, 1.1, 1.1))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(0.5, 1.0, 0.5),
+                ..Default::default()
+            })),
+            Transform::from_xyz(0.0, 0.0, 0.0)
+        ));
+    }
+}
+
 fn perform_attack(
     time: Res<Time>,
     mut commands: Commands,
+    catalogue: Res<AttackCatalogue>,
     mut attacks: Query<(Entity, &ChildOf, &mut Counter)>,
     mut parent_query: Query<&mut ActionState>,
-    mut meshes: ResMut<Assets<Mesh>>, 
-    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    for (child_entity, parent, mut attack) in &mut attacks {
-        let initialize_counter : bool = attack.timer.elapsed_secs() == 0.0;
+    let Some(spec) = catalogue.0.get(&key_of::<Counter>()) else { return };
 
-        attack.timer.tick(time.delta());
+    for (child_entity, parent, mut attack) in &mut attacks {
+        attack.elapsed += time.delta_secs();
         println!("Performing counter");
         if let Ok(mut state) = parent_query.get_mut(parent.0) {
             println!("Performing parent search");
             *state = ActionState::Attacking;
-            if initialize_counter {
-                commands.entity(child_entity).insert((
-                    Mesh3d(meshes.add(Cuboid::new(1.1, 1.1, 1.1))),
-                    MeshMaterial3d(materials.add(StandardMaterial {
-                        base_color: Color::srgb(0.5, 1.0, 0.5),
-                        ..Default::default()
-                    })),
-                    Transform::from_xyz(0.0, 0.0, 0.0)
-                ));
-            }
             
-            if attack.timer.finished() {
+            if attack.elapsed >= spec.duration() {
                 println!("REMOVING");
                 commands.entity(child_entity).despawn();
                 *state = ActionState::Idle;
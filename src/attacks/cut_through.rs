@@ -2,17 +2,15 @@ use bevy::prelude::*;
 use crate::components::humanoid::{ActionState, PositionUpdate};
 use crate::components::island::OnIsland;
 use crate::components::island_maps::{IslandMaps, Tile, TileType};
-use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, AttackSpec, DamageEvent, Interruptable};
+use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, DamageEvent, Interruptable};
+use crate::plugins::lag_compensation::HitTargets;
 use crate::preludes::humanoid_preludes::*;
-use crate::components::enemy::STANDARD;
-
-const DAMAGE: u64 = 20;
 
 #[derive(Component)]
 #[require(Interruptable)]
 pub struct CutThrough {
     direction: IVec3,
-    timer: Timer,
+    elapsed: f32,
     hit: bool
 }
 
@@ -20,7 +18,7 @@ impl Default for CutThrough {
     fn default() -> Self {
         CutThrough { 
             direction: IVec3::X, 
-            timer: Timer::from_seconds(0.0, TimerMode::Once),
+            elapsed: 0.0,
             hit: false
         }
     }
@@ -37,54 +35,62 @@ impl Plugin for CutThroughPlugin {
 
 fn register_base_attack(
     mut registry: ResMut<AttackRegistry>,
-    mut catalog: ResMut<AttackCatalogue>,
 ) {
     registry.register::<CutThrough>(|commands, entity, offset| {
         if let Ok(mut ec) = commands.get_entity(entity) {
             ec.insert(CutThrough {
                 direction: offset,
-                timer: Timer::from_seconds(0.00, TimerMode::Once),
-                hit: false,
+                ..default()
             });
         } 
     });
-    let key = key_of::<CutThrough>();
-    catalog.0.insert(key, AttackSpec {offsets: &STANDARD, cooldown: 6.0, damage: DAMAGE });
 }
 
 fn perform_attack(
     time: Res<Time>,
     mut commands: Commands,
+    catalogue: Res<AttackCatalogue>,
     mut event: EventWriter<PositionUpdate>,
     mut attacks: Query<(Entity, &ChildOf, &mut CutThrough)>,
     mut parent_query: Query<(&mut Position, &mut ActionState, &OnIsland)>,
-    mut island_maps: ResMut<IslandMaps>
+    island_maps: Res<IslandMaps>,
+    targets: HitTargets,
 ) {
+    let attack_id = key_of::<CutThrough>();
+    let Some(spec) = catalogue.0.get(&attack_id) else { return };
+
     for (child_entity, parent, mut attack) in &mut attacks {
-        attack.timer.tick(time.delta());
+        attack.elapsed += time.delta_secs();
 
         if let Ok((mut pos, mut state, island)) = parent_query.get_mut(parent.0) {
             *state = ActionState::Attacking;
 
-            if attack.timer.finished() {
+            // Cuts through up to `range` targets in a row and lands behind the last one
+            if !attack.hit && attack.elapsed >= spec.windup {
+                attack.hit = true;
                 let mut check_pos = pos.0 + attack.direction;
-                if let Some(map) = island_maps.get_map_mut(island.0) {
-                    while map.get_target(check_pos).is_some() {
+                if let Some(map) = island_maps.get_map(island.0) {
+                    let mut cut = 0;
+                    while cut < spec.range && targets.target(parent.0, island.0, check_pos).is_some() {
                         commands.trigger(DamageEvent::new(
                             parent.0,
                             island.0,
                             check_pos,
-                            DAMAGE
+                            spec.damage,
+                            attack_id
                         ));
 
                         check_pos += attack.direction;
+                        cut += 1;
                     }
 
                     if map.can_move(check_pos) {
                         event.write(PositionUpdate { new_position: check_pos, entity: parent.0 });
                     }
                 }
+            }
 
+            if attack.elapsed >= spec.duration() {
                 commands.entity(child_entity).despawn();
                 *state = ActionState::Idle;
             }
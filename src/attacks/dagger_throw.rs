@@ -2,21 +2,17 @@ use bevy::prelude::*;
 use crate::components::humanoid::ActionState;
 use crate::components::island::OnIsland;
 use crate::components::island_maps::IslandMaps;
-use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, AttackSpec, Interruptable};
+use crate::plugins::attack::{key_of, AttackCatalogue, AttackRegistry, Interruptable};
 use crate::plugins::projectiles::Projectile;
 use crate::preludes::humanoid_preludes::*;
-use crate::components::enemy::STANDARD;
 use crate::attacks::core::check_attack_path;
 
-const DAMAGE: u64 = 8;
-const ATTACK_RANGE : u8 = 8;
-
 
 #[derive(Component)]
 #[require(Interruptable)]
 pub struct DaggerThrow {
     direction: IVec3,
-    timer: Timer,
+    elapsed: f32,
     hit: bool
 }
 
@@ -24,7 +20,7 @@ impl Default for DaggerThrow {
     fn default() -> Self {
         DaggerThrow { 
             direction: IVec3::X, 
-            timer: Timer::from_seconds(0.1, TimerMode::Once),
+            elapsed: 0.0,
             hit: false
         }
     }
@@ -41,65 +37,59 @@ impl Plugin for DaggerThrowPlugin {
 
 fn register_attack(
     mut registry: ResMut<AttackRegistry>,
-    mut catalog: ResMut<AttackCatalogue>,
 ) {
     registry.register::<DaggerThrow>(|commands, entity, offset| {
         if let Ok(mut ec) = commands.get_entity(entity) {
             ec.insert(DaggerThrow {
                 direction: offset,
-                timer: Timer::from_seconds(0.20, TimerMode::Once),
-                hit: false,
+                ..default()
             });
         }
     });
-    let key = key_of::<DaggerThrow>();
-    catalog.0.insert(key, AttackSpec {offsets: &STANDARD, cooldown: 0.8, damage: DAMAGE });
 }
 
 fn perform_attack(
     time: Res<Time>,
     mut commands: Commands,
+    catalogue: Res<AttackCatalogue>,
     island_maps: Res<IslandMaps>,
-    mut meshes: ResMut<Assets<Mesh>>, 
-    mut materials: ResMut<Assets<StandardMaterial>>,
     mut attacks: Query<(Entity, &ChildOf, &mut DaggerThrow)>,
     mut parent_query: Query<(&Position, &mut ActionState, &OnIsland)>,
 ) {
+    let attack_id = key_of::<DaggerThrow>();
+    let Some(spec) = catalogue.0.get(&attack_id) else { return };
+
         for (child_entity, parent, mut attack) in &mut attacks {
         if let Ok((pos, mut state, island)) = parent_query.get_mut(parent.0) {
             *state = ActionState::Attacking;
-            attack.timer.tick(time.delta());
+            attack.elapsed += time.delta_secs();
 
-            if island_maps.get_map(island.0).is_some() && !attack.hit { //TODO: using attack.hit to ensure only one projectile is spawned, not ideal but works for now
+            if island_maps.get_map(island.0).is_some() && !attack.hit && attack.elapsed >= spec.windup { //TODO: using attack.hit to ensure only one projectile is spawned, not ideal but works for now
                 attack.hit = true;
             
                 let attack_direction = check_attack_path(
                     pos.0,
                     attack.direction,
-                    ATTACK_RANGE,
+                    spec.range,
                     island_maps.get_map(island.0).unwrap()
                 );
 
                 commands.spawn((
-                    Mesh3d(meshes.add(Cuboid::new(0.3, 0.3, 0.3))),
-                    MeshMaterial3d(materials.add(StandardMaterial {
-                        base_color: Color::srgb(1.0, 1.0, 1.0),
-                        ..Default::default()
-                    })),
                     Projectile {
                         owner: parent.0,
                         traveled: 0.0,
-                        range: ATTACK_RANGE,
+                        range: spec.range,
                         direction: attack_direction, //Vec3::new(attack.direction.x as f32, attack.direction.y as f32, attack.direction.z as f32),
                         speed: 16.0,
-                        damage: DAMAGE
+                        damage: spec.damage,
+                        attack_id
                     },
                     Transform::from_translation(pos.0.as_vec3()),
                     OnIsland(island.0)
                 ));
             }
 
-            if attack.timer.finished() {
+            if attack.elapsed >= spec.duration() {
                 commands.entity(child_entity).despawn();
                 *state = ActionState::Idle;
             }
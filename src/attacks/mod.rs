@@ -3,3 +3,4 @@ pub mod base_attack;
 pub mod dagger_throw;
 pub mod cut_through;
 pub mod counter;
+pub mod script;
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use crate::attacks::core::check_attack_path;
+use crate::components::humanoid::{ActionState, PositionUpdate, StatusEffect};
+use crate::components::island::OnIsland;
+use crate::components::island_maps::IslandMaps;
+use crate::plugins::attack::{AttackCatalogue, AttackId, DamageEvent, Interruptable, NegatedDamageEvent, NegatingDamage};
+use crate::plugins::lag_compensation::HitTargets;
+use crate::plugins::projectiles::Projectile;
+use crate::preludes::humanoid_preludes::*;
+
+/// One step of an attack script from `assets/attacks.spec.ron`. Steps run in order, patterns are
+/// written facing +X and turned towards the attack direction. Damage, range and on-hit effects come from the attack's spec.
+#[derive(Clone, Debug, Deserialize)]
+pub enum AttackStep {
+    /// Seconds before the next step, a stun cancels the attack
+    Windup(f32),
+    /// Seconds before the next step
+    Wait(f32),
+    /// Moves up to `tiles` forward, with `cut_through` the attacker passes through and hits targets in the way instead of stopping
+    Dash { tiles: u8, #[serde(default)] cut_through: bool },
+    /// Hits every tile of the pattern
+    Area(Vec<IVec3>),
+    /// Throws a projectile that flies for the attack's range
+    Projectile { speed: f32 },
+    /// Puts a status on whatever stands on the pattern without hitting it, an empty pattern means the attacker itself
+    Status { effect: StatusEffect, #[serde(default)] pattern: Vec<IVec3> },
+    /// Seconds in which damage to the attacker is negated, an attacker that runs into it gets the on-hit effects and the script moves on
+    NegateDamage(f32),
+}
+
+/// A running attack script, spawned by the `AttackRegistry` for attacks that only exist as data.
+#[derive(Component)]
+pub struct ScriptedAttack {
+    attack_id: AttackId,
+    direction: IVec3,
+    step: usize,
+    /// Time spent in the current step
+    elapsed: f32,
+    /// Where the attacker stands after a dash, position updates land after this frame
+    origin: Option<IVec3>,
+}
+
+impl ScriptedAttack {
+    pub fn new(attack_id: AttackId, direction: IVec3) -> Self {
+        Self { attack_id, direction, step: 0, elapsed: 0.0, origin: None }
+    }
+
+    // Turns a pattern offset written facing +X towards the attack direction
+    fn facing(&self, offset: IVec3) -> IVec3 {
+        let side = IVec3::new(-self.direction.z, 0, self.direction.x);
+        self.direction * offset.x + IVec3::Y * offset.y + side * offset.z
+    }
+
+    fn next_step(&mut self, commands: &mut Commands, entity: Entity) {
+        self.step += 1;
+        self.elapsed = 0.0;
+        commands.entity(entity).remove::<(Interruptable, NegatingDamage)>();
+    }
+}
+
+pub struct AttackScriptPlugin;
+impl Plugin for AttackScriptPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, (run_attack_scripts, scripted_negates).chain());
+    }
+}
+
+fn run_attack_scripts(
+    time: Res<Time>,
+    mut commands: Commands,
+    catalogue: Res<AttackCatalogue>,
+    island_maps: Res<IslandMaps>,
+    targets: HitTargets,
+    mut position_updates: EventWriter<PositionUpdate>,
+    mut attacks: Query<(Entity, &ChildOf, &mut ScriptedAttack)>,
+    mut parent_query: Query<(&Position, &mut ActionState, &OnIsland)>,
+) {
+    for (entity, parent, mut script) in &mut attacks {
+        let Ok((pos, mut state, island)) = parent_query.get_mut(parent.0) else { continue };
+        let Some(spec) = catalogue.0.get(&script.attack_id) else { continue };
+        let Some(map) = island_maps.get_map(island.0) else { continue };
+
+        *state = ActionState::Attacking;
+        script.elapsed += time.delta_secs();
+
+        // Instant steps all run this frame, timed steps hold the script until their time is up
+        while let Some(step) = spec.script.get(script.step) {
+            let origin = script.origin.unwrap_or(pos.0);
+
+            match step {
+                AttackStep::Windup(seconds) | AttackStep::Wait(seconds) | AttackStep::NegateDamage(seconds) => {
+                    if script.elapsed < *seconds {
+                        match step {
+                            AttackStep::Windup(_) => { commands.entity(entity).insert(Interruptable); }
+                            AttackStep::NegateDamage(_) => { commands.entity(entity).insert(NegatingDamage(script.attack_id)); }
+                            _ => (),
+                        }
+                        break;
+                    }
+                    let left = script.elapsed - seconds;
+                    script.next_step(&mut commands, entity);
+                    script.elapsed = left;
+                    continue;
+                }
+                AttackStep::Dash { tiles, cut_through } => {
+                    let mut landing = origin;
+                    for tile in 1..=*tiles as i32 {
+                        let check_pos = origin + script.direction * tile;
+                        if targets.target(parent.0, island.0, check_pos).is_some() {
+                            if !cut_through {
+                                break;
+                            }
+                            commands.trigger(DamageEvent::new(parent.0, island.0, check_pos, spec.damage, script.attack_id));
+                        }
+                        else if map.can_move(check_pos) {
+                            landing = check_pos;
+                        }
+                        else {
+                            break;
+                        }
+                    }
+
+                    if landing != origin {
+                        position_updates.write(PositionUpdate { new_position: landing, entity: parent.0 });
+                        script.origin = Some(landing);
+                    }
+                }
+                AttackStep::Area(pattern) => {
+                    for offset in pattern.iter() {
+                        commands.trigger(DamageEvent::new(parent.0, island.0, origin + script.facing(*offset), spec.damage, script.attack_id));
+                    }
+                }
+                AttackStep::Projectile { speed } => {
+                    commands.spawn((
+                        Projectile {
+                            owner: parent.0,
+                            traveled: 0.0,
+                            range: spec.range,
+                            direction: check_attack_path(origin, script.direction, spec.range, map),
+                            speed: *speed,
+                            damage: spec.damage,
+                            attack_id: script.attack_id,
+                        },
+                        Transform::from_translation(origin.as_vec3()),
+                        OnIsland(island.0)
+                    ));
+                }
+                AttackStep::Status { effect, pattern } => {
+                    if pattern.is_empty() {
+                        effect.apply(&mut commands, parent.0);
+                    }
+                    for offset in pattern.iter() {
+                        if let Some(target) = targets.target(parent.0, island.0, origin + script.facing(*offset)) {
+                            effect.apply(&mut commands, target);
+                        }
+                    }
+                }
+            }
+
+            script.step += 1;
+        }
+
+        if script.step >= spec.script.len() {
+            commands.entity(entity).despawn();
+            *state = ActionState::Idle;
+        }
+    }
+}
+
+// Like a counter, whoever runs into negated damage gets the attack's on-hit effects
+fn scripted_negates(
+    mut reader: EventReader<NegatedDamageEvent>,
+    mut commands: Commands,
+    catalogue: Res<AttackCatalogue>,
+    mut attacks: Query<&mut ScriptedAttack, With<NegatingDamage>>,
+) {
+    for event in reader.read() {
+        let Ok(mut script) = attacks.get_mut(event.victim) else { continue };
+        let Some(spec) = catalogue.0.get(&script.attack_id) else { continue };
+
+        for effect in spec.on_hit.iter() {
+            effect.apply(&mut commands, event.owner);
+        }
+        script.next_step(&mut commands, event.victim);
+    }
+}
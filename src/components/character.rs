@@ -1,14 +1,101 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use bevy_replicon::prelude::Replicated;
+use std::collections::VecDeque;
 
+use crate::attacks::base_attack::BaseAttack;
 use crate::components::humanoid::Humanoid;
+use crate::components::player::{ActiveBuffs, ItemStats};
+use crate::plugins::attack::{key_of, AttackId};
 
 #[derive(Component, Serialize, Deserialize, Debug)]
 #[require(Humanoid)]
 #[require(Replicated)]
+#[require(LastMoveInput)]
+#[require(MovePrediction)]
+#[require(Loadout)]
+#[require(ActiveBuffs)]
+#[require(CombatStats)]
+#[require(StepBudget)]
 pub struct Character;
 
+pub const BASE_MAX_HEALTH: u64 = 200;
+/// Time between steps while a movement key is held, at normal move speed.
+pub const STEP_SECONDS: f32 = 0.2;
+/// Shortest time between two tapped steps, at normal move speed.
+pub const TAP_STEP_SECONDS: f32 = 0.05;
+/// Steps a character can bank, so inputs bunched up by the network are not rejected.
+pub const MAX_STEP_BURST: f32 = 3.0;
+
+/// A character's stats after equipment and buffs, worked out on the server.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CombatStats {
+    pub max_health: u64,
+    /// Multiplier on the damage of every attack
+    pub damage: f32,
+    /// Multiplier on attack cooldowns
+    pub cooldown: f32,
+    /// Multiplier on steps per second
+    pub move_speed: f32,
+}
+
+impl Default for CombatStats {
+    fn default() -> Self {
+        CombatStats { max_health: BASE_MAX_HEALTH, damage: 1.0, cooldown: 1.0, move_speed: 1.0 }
+    }
+}
+
+impl CombatStats {
+    pub fn from_bonus(bonus: ItemStats) -> Self {
+        CombatStats {
+            max_health: BASE_MAX_HEALTH.saturating_add_signed(bonus.max_health).max(1),
+            damage: (1.0 + bonus.damage).max(0.0),
+            cooldown: (1.0 - bonus.cooldown_reduction).max(0.25),
+            move_speed: (1.0 + bonus.move_speed).max(0.25),
+        }
+    }
+
+    pub fn scale_damage(&self, damage: u64) -> u64 {
+        (damage as f32 * self.damage).round() as u64
+    }
+
+    pub fn scale_cooldown(&self, seconds: f32) -> f32 {
+        seconds * self.cooldown
+    }
+}
+
+/// Steps the server still accepts from this character right now, refilled by its move speed.
+#[derive(Component)]
+pub struct StepBudget(pub f32);
+
+impl Default for StepBudget {
+    fn default() -> Self {
+        StepBudget(MAX_STEP_BURST)
+    }
+}
+
+/// Attacks this character is allowed to cast, the server rejects anything else. Kept in sync
+/// with the owner's `SkillLoadout`, the base attack is always allowed.
+#[derive(Component)]
+pub struct Loadout(pub Vec<AttackId>);
+
+impl Default for Loadout {
+    fn default() -> Self {
+        Loadout(vec![key_of::<BaseAttack>()])
+    }
+}
+
+/// Sequence of the last movement input the server processed for this character.
+#[derive(Component, Default)]
+pub struct LastMoveInput(pub u32);
+
+/// Movement inputs the local player already applied but the server has not acknowledged yet.
+#[derive(Component, Default)]
+pub struct MovePrediction {
+    pub next_sequence: u32,
+    pub pending: VecDeque<(u32, IVec3)>,
+}
+
 #[derive(Component)]
 pub struct LocalPlayer;
 
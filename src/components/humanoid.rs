@@ -39,7 +39,8 @@ pub struct PositionUpdate {
 
 #[derive(Debug, Default, Deserialize, Event, Serialize)]
 pub struct ServerPositionUpdate {
-    pub position: IVec3
+    pub position: IVec3,
+    pub last_input: u32,
 }
 
 #[derive(Component, Serialize, Deserialize, Default)]
@@ -61,7 +62,10 @@ impl Default for ViewDirection {
 }
 
 #[derive(Debug, Default, Deserialize, Event, Serialize)]
-pub struct MoveDirection(pub IVec3);
+pub struct MoveDirection {
+    pub direction: IVec3,
+    pub sequence: u32,
+}
 
 #[derive(Component, Serialize, Deserialize)]
 pub struct RemoveEntity;
@@ -81,6 +85,7 @@ pub enum ActionState {
 #[require(Health)]
 #[require(ActionState)]
 #[require(AttackCooldowns)]
+#[require(AttackWindow)]
 #[require(StatusFlags)]
 #[require(ActiveSkills)]
 pub struct Humanoid;
@@ -88,6 +93,10 @@ pub struct Humanoid;
 #[derive(Component, Default)]
 pub struct AttackCooldowns(pub HashMap<AttackId, Timer>);
 
+/// Windup plus active time left of the last attack the server accepted from the owning client.
+#[derive(Component, Default)]
+pub struct AttackWindow(pub Timer);
+
 bitflags! {
     #[derive(Default)]
     pub struct Status: u8 {
@@ -112,6 +121,22 @@ impl Stunned {
     }
 }
 
+/// Status an attack puts on what it hits, listed per attack in `assets/attacks.spec.ron`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum StatusEffect {
+    Stun { seconds: f32 },
+}
+
+impl StatusEffect {
+    pub fn apply(&self, commands: &mut Commands, target: Entity) {
+        match *self {
+            StatusEffect::Stun { seconds } => {
+                commands.entity(target).try_insert(Stunned::new(seconds));
+            }
+        }
+    }
+}
+
 #[derive(Component, Default)]
 pub struct ActiveSkills(pub HashMap<u64, Entity>);
 
@@ -7,6 +7,10 @@ pub struct EleminationObjective;
 #[derive(Component)]
 pub struct IslandRoot;
 
+/// Visual of one terrain tile, lets single tiles be replaced when the map changes.
+#[derive(Component)]
+pub struct TerrainBlock(pub IVec3);
+
 #[derive(Debug, Deserialize, Event, Serialize)]
 pub struct EnteredIsland(pub u64);
 
@@ -44,4 +48,26 @@ pub struct EliminationObjective;
 pub struct CompletedIslandObjective;
 
 #[derive(Component)]
-pub struct Chest;
\ No newline at end of file
+pub struct Chest;
+
+/// How good a reward chest is, each tier has its own loot table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChestTier {
+    #[default]
+    Common,
+    Rare,
+    Legendary,
+}
+
+impl ChestTier {
+    /// Chance of each tier out of 100, rolled when an island is completed.
+    pub const WEIGHTS: [(ChestTier, u32); 3] = [(ChestTier::Common, 70), (ChestTier::Rare, 25), (ChestTier::Legendary, 5)];
+
+    pub fn loot_table(&self) -> &'static str {
+        match self {
+            ChestTier::Common => "chest_common",
+            ChestTier::Rare => "chest_rare",
+            ChestTier::Legendary => "chest_legendary",
+        }
+    }
+}
\ No newline at end of file
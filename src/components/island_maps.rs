@@ -107,7 +107,8 @@ pub struct Map {
     pub player_count : u32,
     pub enemy_count : u32,
     pub leave_position : IVec3,
-    pub entities: HashSet<Entity>
+    pub entities: HashSet<Entity>,
+    pub version: u32,
 }
 
 impl Map {
@@ -117,8 +118,9 @@ impl Map {
         let enemy_count = 0;
         let leave_position = IVec3::ZERO;
         let entities = HashSet::new();
+        let version = 0;
 
-        Map { chunks, player_count, enemy_count, leave_position, entities }
+        Map { chunks, player_count, enemy_count, leave_position, entities, version }
     }
 
     pub fn world_to_chunk_coords(&self, world_pos: IVec3) -> IVec3 {
@@ -184,6 +186,44 @@ impl Map {
         }
     }
 
+    // Resolve a single grid step, stepping up or down one block where the terrain allows it
+    pub fn step_target(&self, position: IVec3, direction: IVec3) -> Option<IVec3> {
+        let mut new_position = position + direction;
+
+        match self.get_tile(new_position).kind {
+            TileType::Terrain(_) => {
+                new_position.y += 1;
+                if self.get_tile(new_position).kind != TileType::Empty {
+                    return None;
+                }
+            }
+            TileType::Empty => {
+                if new_position.y != 0 {
+                    let mut temp_pos = new_position;
+                    temp_pos.y -= 1;
+
+                    if self.get_tile(temp_pos).kind == TileType::Empty {
+                        temp_pos.y -= 1;
+                        if !matches!(self.get_tile(temp_pos).kind, TileType::Terrain(_)) {
+                            return None;
+                        }
+                        new_position.y -= 1;
+                    }
+                }
+            }
+            _ => return None,
+        }
+
+        Some(new_position)
+    }
+
+    // Only clears the tile when it still belongs to the entity, used when moving predicted entities
+    pub fn remove_entity_if(&mut self, position: IVec3, entity: Entity) {
+        if self.get_tile(position).entity == entity {
+            self.remove_entity(position);
+        }
+    }
+
     // Get a tile at the world position
     pub fn get_tile(&self, position: IVec3) -> Tile {
         if let Some(chunk) = self.get_chunk(position) {
@@ -229,6 +269,12 @@ impl Map {
         self.entities.insert(entity);
     }
 
+    pub fn remove_player(&mut self, position: IVec3, entity: Entity) {
+        self.player_count = self.player_count.saturating_sub(1);
+        self.remove_entity(position);
+        self.entities.remove(&entity);
+    }
+
     pub fn add_enemy(&mut self, position: IVec3, entity: Entity){
         self.enemy_count += 1;
         self.add_entity_ivec3(position, Tile::new(TileType::Enemy, entity));
@@ -240,6 +286,36 @@ impl Map {
         self.add_entity_ivec3(position, Tile::new(tile_type, entity));
     }
 
+    // Replace the terrain of a single tile, `None` digs it out
+    pub fn set_terrain(&mut self, position: IVec3, terrain: Option<TerrainType>) {
+        match terrain {
+            Some(terrain) => self.add_entity_ivec3(position, Tile::new(TileType::Terrain(terrain), Entity::PLACEHOLDER)),
+            None => self.remove_entity(position),
+        }
+    }
+
+    // Terrain of one chunk as (tile index, terrain) pairs, entities are synced separately
+    pub fn chunk_terrain(&self, coords: IVec3) -> Vec<(u16, TerrainType)> {
+        let Some(chunk) = self.chunks.get(&coords) else {
+            return Vec::new();
+        };
+
+        chunk.tiles.iter()
+            .enumerate()
+            .filter_map(|(index, tile)| match tile.kind {
+                TileType::Terrain(terrain) => Some((index as u16, terrain)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn load_chunk_terrain(&mut self, coords: IVec3, terrain: &[(u16, TerrainType)]) {
+        let chunk = self.chunks.entry(coords).or_insert_with(Chunk::new);
+        for (index, terrain) in terrain {
+            chunk.tiles[*index as usize] = Tile::new(TileType::Terrain(*terrain), Entity::PLACEHOLDER);
+        }
+    }
+
     pub fn shore_tiles(&mut self) -> Vec<IVec3> {
         let neighbors = [
             IVec3::X,
@@ -337,12 +413,41 @@ impl Map {
         }
     }
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub enum UpdateType {
-    LoadTerrain,
-    UnloadTerrain,
+    /// Terrain of one chunk, part of a snapshot
+    Chunk { coords: IVec3, terrain: Vec<(u16, TerrainType)> },
+    /// Closes a snapshot, lets the client check it received every chunk
+    SnapshotEnd { chunks: u32 },
+    /// Terrain change of a single tile, `None` clears it
+    Tile { position: IVec3, terrain: Option<TerrainType> },
+}
+
+/// Map contents streamed from the server. Snapshots carry the map version they were taken at,
+/// every tile delta after that bumps the version by exactly one.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct MapUpdate {
+    pub island: u64,
+    pub version: u32,
+    pub update: UpdateType,
 }
 
+/// Sent by a client that missed a delta, the server answers with a fresh snapshot.
 #[derive(Debug, Deserialize, Event, Serialize)]
-pub struct MapUpdate(pub UpdateType, pub IVec3, pub u32);
+pub struct MapResyncRequest(pub u64);
+
+/// Server side request to change terrain on an island, synced to the players on it.
+#[derive(Event, Debug)]
+pub struct TerrainChange {
+    pub island: u64,
+    pub position: IVec3,
+    pub terrain: Option<TerrainType>,
+}
+
+/// Raised on every peer after a terrain tile changed, so the visuals can follow.
+#[derive(Event, Debug)]
+pub struct TerrainChanged {
+    pub island: u64,
+    pub position: IVec3,
+}
 
@@ -1,11 +1,37 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use bevy_replicon::prelude::Replicated;
+use std::collections::VecDeque;
 
 #[derive(Component, Serialize, Deserialize, Debug)]
-#[require(Replicated)]
+#[require(Replicated, ShipMotion, ShipPrediction)]
 pub struct Ship;
 
+/// Movement state integrated by the server, and by the owning client for prediction.
+#[derive(Component, Default)]
+pub struct ShipMotion {
+    pub direction: Vec3,
+    pub velocity: Vec3,
+    pub last_input: u32,
+}
+
+pub struct ShipInputRecord {
+    pub sequence: u32,
+    pub direction: Vec3,
+    pub delta: f32,
+}
+
+/// Inputs of the local ship that the server has not acknowledged yet.
+#[derive(Component, Default)]
+pub struct ShipPrediction {
+    pub next_sequence: u32,
+    pub pending: VecDeque<ShipInputRecord>,
+}
+
+/// Saved overworld position of the owner, a new ship starts there instead of at the harbour.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+pub struct ShipSpawnPosition(pub Vec3);
+
 #[derive(Component)]
 pub struct Ocean;
 
@@ -16,6 +42,9 @@ pub struct Island(pub u64);
 #[require(Island)]
 pub struct StarterIsland;
 
+#[derive(Component)]
+pub struct IslandTint(pub Color);
+
 #[derive(Component)]
 pub struct LocalIsland;
 
@@ -29,12 +58,16 @@ pub struct OverworldUI;
 pub struct OverworldRoot;
 
 #[derive(Debug, Deserialize, Event, Serialize)]
-pub struct ClientShipPosition(pub Vec3);
+pub struct ClientShipInput {
+    pub direction: Vec3,
+    pub sequence: u32,
+}
 
 #[derive(Debug, Deserialize, Event, Serialize)]
 pub struct ServerShipPosition{
-    pub client_entity: Entity,
-    pub position: Vec3
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub last_input: u32,
 }
 
 #[derive(Resource, Clone, Copy)]
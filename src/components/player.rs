@@ -1,39 +1,702 @@
 use std::collections::HashMap;
 use bevy::prelude::*;
+use bevy_replicon::prelude::Replicated;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Default)]
+use crate::plugins::attack::AttackId;
+
+pub const INVENTORY_SLOTS: usize = 20;
+
+/// Server owned entity holding a player's items and progress, replicated to that player only.
+#[derive(Component, Serialize, Deserialize)]
+#[require(Replicated)]
+#[require(Equipment)]
+#[require(CharacterXp)]
+#[require(Gold)]
+#[require(UnlockedSkills)]
+#[require(SkillLoadout)]
+pub struct PlayerData;
+
+/// Items a player wears. Kept next to the inventory instead of on the island character,
+/// so it stays equipped between islands.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Equipment {
+    pub weapon: Option<ItemId>,
+    pub armor: Option<ItemId>,
+    pub trinket: Option<ItemId>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<ItemId> {
+        match slot {
+            EquipmentSlot::Weapon => self.weapon,
+            EquipmentSlot::Armor => self.armor,
+            EquipmentSlot::Trinket => self.trinket,
+        }
+    }
+
+    pub fn slot_mut(&mut self, slot: EquipmentSlot) -> &mut Option<ItemId> {
+        match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::Trinket => &mut self.trinket,
+        }
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = ItemId> {
+        [self.weapon, self.armor, self.trinket].into_iter().flatten()
+    }
+}
+
+#[derive(Component, Serialize, Deserialize)]
 pub struct Inventory {
     pub slots: Vec<Option<ItemStack>>,
 }
 
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory { slots: vec![None; INVENTORY_SLOTS] }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InventoryError {
+    InvalidSlot,
+    EmptySlot,
+    NotEnough,
+    NoFreeSlot,
+}
+
+impl Inventory {
+    /// Restores saved slots, padded up to the inventory size.
+    pub fn from_slots(mut slots: Vec<Option<ItemStack>>) -> Self {
+        if slots.len() < INVENTORY_SLOTS {
+            slots.resize(INVENTORY_SLOTS, None);
+        }
+        Inventory { slots }
+    }
+
+    pub fn count(&self, id: ItemId) -> u32 {
+        self.slots.iter().flatten().filter(|stack| stack.id == id).map(|stack| stack.qty as u32).sum()
+    }
+
+    /// Tops up existing stacks first, then fills empty slots. Returns what did not fit.
+    pub fn add(&mut self, stack: ItemStack, catalogue: &ItemCatalogue) -> Option<ItemStack> {
+        let max = catalogue.max_stack(stack.id);
+        let mut remaining = stack.qty;
+
+        for existing in self.slots.iter_mut().flatten().filter(|existing| existing.id == stack.id) {
+            let moved = remaining.min(max.saturating_sub(existing.qty));
+            existing.qty += moved;
+            remaining -= moved;
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if remaining == 0 {
+                break;
+            }
+            let moved = remaining.min(max);
+            *slot = Some(ItemStack { id: stack.id, qty: moved });
+            remaining -= moved;
+        }
+
+        (remaining > 0).then_some(ItemStack { id: stack.id, qty: remaining })
+    }
+
+    /// Takes `qty` of an item spread over any slots, nothing is taken when there is not enough.
+    pub fn remove(&mut self, id: ItemId, qty: u16) -> Result<(), InventoryError> {
+        if self.count(id) < qty as u32 {
+            return Err(InventoryError::NotEnough);
+        }
+
+        let mut remaining = qty;
+        // Emptying the last stacks first keeps the front of the inventory stable
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.id == id) else {
+                continue;
+            };
+
+            let taken = remaining.min(stack.qty);
+            stack.qty -= taken;
+            remaining -= taken;
+            if stack.qty == 0 {
+                *slot = None;
+            }
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether `add` would take all of `stack`.
+    pub fn fits(&self, stack: &ItemStack, catalogue: &ItemCatalogue) -> bool {
+        let max = catalogue.max_stack(stack.id) as u32;
+        let room: u32 = self.slots.iter().map(|slot| match slot {
+            Some(existing) if existing.id == stack.id => max.saturating_sub(existing.qty as u32),
+            Some(_) => 0,
+            None => max,
+        }).sum();
+        room >= stack.qty as u32
+    }
+
+    /// Takes up to `qty` from one slot.
+    pub fn take_from_slot(&mut self, slot: usize, qty: u16) -> Result<ItemStack, InventoryError> {
+        let entry = self.slots.get_mut(slot).ok_or(InventoryError::InvalidSlot)?;
+        let stack = entry.as_mut().ok_or(InventoryError::EmptySlot)?;
+
+        let taken = ItemStack { id: stack.id, qty: qty.min(stack.qty) };
+        stack.qty -= taken.qty;
+        if stack.qty == 0 {
+            *entry = None;
+        }
+        Ok(taken)
+    }
+
+    /// Moves a stack onto another slot. Same items merge as far as the stack size allows,
+    /// different items swap places.
+    pub fn move_stack(&mut self, from: usize, to: usize, catalogue: &ItemCatalogue) -> Result<(), InventoryError> {
+        if from >= self.slots.len() || to >= self.slots.len() {
+            return Err(InventoryError::InvalidSlot);
+        }
+        if self.slots[from].is_none() {
+            return Err(InventoryError::EmptySlot);
+        }
+        if from == to {
+            return Ok(());
+        }
+
+        let same_item = matches!((&self.slots[from], &self.slots[to]), (Some(source), Some(target)) if source.id == target.id);
+        if same_item {
+            self.merge(from, to, catalogue)?;
+        } else {
+            self.slots.swap(from, to);
+        }
+        Ok(())
+    }
+
+    /// Moves `qty` out of a stack into the first empty slot, returns that slot.
+    pub fn split(&mut self, slot: usize, qty: u16) -> Result<usize, InventoryError> {
+        let stack = self.slots.get(slot).ok_or(InventoryError::InvalidSlot)?.as_ref().ok_or(InventoryError::EmptySlot)?;
+        if qty == 0 || qty >= stack.qty {
+            return Err(InventoryError::NotEnough);
+        }
+
+        let free = self.slots.iter().position(|slot| slot.is_none()).ok_or(InventoryError::NoFreeSlot)?;
+        let taken = self.take_from_slot(slot, qty)?;
+        self.slots[free] = Some(taken);
+        Ok(free)
+    }
+
+    /// Pours one stack into another of the same item, returns how many moved.
+    pub fn merge(&mut self, from: usize, to: usize, catalogue: &ItemCatalogue) -> Result<u16, InventoryError> {
+        if from >= self.slots.len() || to >= self.slots.len() || from == to {
+            return Err(InventoryError::InvalidSlot);
+        }
+
+        let (Some(source), Some(target)) = (&self.slots[from], &self.slots[to]) else {
+            return Err(InventoryError::EmptySlot);
+        };
+        if source.id != target.id {
+            return Err(InventoryError::InvalidSlot);
+        }
+
+        let moved = source.qty.min(catalogue.max_stack(target.id).saturating_sub(target.qty));
+        self.take_from_slot(from, moved)?;
+        if let Some(target) = self.slots[to].as_mut() {
+            target.qty += moved;
+        }
+        Ok(moved)
+    }
+}
+
 pub type ItemId = u64;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ItemStack { pub id: ItemId, pub qty: u16 }
 
-#[derive(Event)]
-pub struct RewardEvent {
-    pub items: Option<Vec<ItemStack>>,
-    pub xp: u64,
-    pub gold: u64,
+/// Items that did not fit in the inventory, moved back by the server as soon as there is room.
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct Stash {
+    pub items: Vec<ItemStack>,
+}
+
+impl Stash {
+    pub fn add(&mut self, stack: ItemStack) {
+        match self.items.iter_mut().find(|stored| stored.id == stack.id && stored.qty < u16::MAX) {
+            Some(stored) => {
+                let moved = stack.qty.min(u16::MAX - stored.qty);
+                stored.qty += moved;
+                if moved < stack.qty {
+                    self.items.push(ItemStack { id: stack.id, qty: stack.qty - moved });
+                }
+            }
+            None => self.items.push(stack),
+        }
+    }
+
+    /// Moves as much as fits into the inventory, returns whether anything moved.
+    pub fn move_into(&mut self, inventory: &mut Inventory, catalogue: &ItemCatalogue) -> bool {
+        let mut moved = false;
+        let mut kept = Vec::new();
+        for stack in self.items.drain(..) {
+            let qty = stack.qty;
+            if let Some(leftover) = inventory.add(stack, catalogue) {
+                moved |= leftover.qty < qty;
+                kept.push(leftover);
+            } else {
+                moved = true;
+            }
+        }
+        self.items = kept;
+        moved
+    }
 }
 
 #[derive(Event)]
 pub struct SaveEvent;
 
-#[derive(Clone)]
-pub struct ItemSpec { pub name: &'static str, pub max: u16,}
+/// Asks the server to use the item in an inventory slot.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct UseItem {
+    pub slot: usize,
+    pub item: ItemId,
+}
+
+/// Asks the server to move an equipped item back into the inventory.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct UnequipItem {
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ItemUseRejection {
+    NoSuchItem,
+    NotUsable,
+    NotOnIsland,
+    Dead,
+    FullHealth,
+    InventoryFull,
+    AlreadyKnown,
+}
+
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct ItemUseRejected {
+    pub item: ItemId,
+    pub reason: ItemUseRejection,
+}
+
+/// One item as defined in the `assets/items` data files.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ItemSpec {
+    pub id: ItemId,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Stack size, the most of this item one inventory slot holds
+    pub max: u16,
+    #[serde(default)]
+    pub rarity: Rarity,
+    /// Gold a merchant asks for one, items without a price cannot be bought or sold
+    #[serde(default)]
+    pub price: Option<u64>,
+    /// Image path relative to the assets folder
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub effect: Option<ItemEffect>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+/// What an item does when it is used or equipped.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub enum ItemEffect {
+    Heal(u64),
+    Buff { stats: ItemStats, seconds: f32 },
+    /// Attack name as registered in the `AttackRegistry`, e.g. `"DaggerThrow"`
+    UnlockSkill(String),
+    Equipment { slot: EquipmentSlot, stats: ItemStats },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+/// Temporary stat change from a consumable.
+pub struct Buff {
+    pub stats: ItemStats,
+    pub timer: Timer,
+}
+
+#[derive(Component, Default)]
+pub struct ActiveBuffs(pub Vec<Buff>);
+
+/// Stat changes granted by equipment and buffs, zero means no change.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ItemStats {
+    pub max_health: i64,
+    /// Added to the damage multiplier, 0.1 is 10% more damage
+    pub damage: f32,
+    /// Fraction taken off attack cooldowns
+    pub cooldown_reduction: f32,
+    /// Fraction added to movement speed
+    pub move_speed: f32,
+}
+
+impl std::ops::AddAssign for ItemStats {
+    fn add_assign(&mut self, other: Self) {
+        self.max_health += other.max_health;
+        self.damage += other.damage;
+        self.cooldown_reduction += other.cooldown_reduction;
+        self.move_speed += other.move_speed;
+    }
+}
 
 #[derive(Resource, Default)]
 pub struct ItemCatalogue(pub HashMap<ItemId, ItemSpec>);
 
-#[derive(Component)]
+impl ItemCatalogue {
+    /// Unknown items never stack, so nothing is lost while the catalogue is still loading.
+    pub fn max_stack(&self, id: ItemId) -> u16 {
+        self.0.get(&id).map_or(1, |spec| spec.max.max(1))
+    }
+}
+
+/// Lives on the player's `PlayerData`, only the server changes it.
+#[derive(Component, Serialize, Deserialize)]
 pub struct CharacterXp {
+    /// Xp towards the next level
     pub value: u64,
     pub level: u64
 }
 
-#[derive(Component)]
+impl Default for CharacterXp {
+    fn default() -> Self {
+        CharacterXp { value: 0, level: 1 }
+    }
+}
+
+/// Sent by the server to a player that just reached `level`.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct LevelUp {
+    pub level: u64,
+}
+
+/// Lives on the player's `PlayerData`, only the server changes it.
+#[derive(Component, Default, Serialize, Deserialize)]
 pub struct Gold {
     pub value: u128,
-}
\ No newline at end of file
+}
+
+pub const SKILL_SLOTS: usize = 4;
+
+/// Skills the player has unlocked so far, lives on the player's `PlayerData`.
+#[derive(Component, Default, Serialize, Deserialize)]
+pub struct UnlockedSkills {
+    pub skills: Vec<AttackId>,
+    /// Skill points that went into the skill tree, skills learned from items cost none
+    pub points_spent: u32,
+}
+
+/// Skills the player put on the skill keys, lives on the player's `PlayerData`.
+#[derive(Component, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SkillLoadout(pub [Option<AttackId>; SKILL_SLOTS]);
+
+impl SkillLoadout {
+    /// Restores saved slots, skills that are no longer unlocked are left out. Saves from before
+    /// there was a loadout get the first unlocked skills.
+    pub fn from_saved(slots: &[Option<AttackId>], unlocked: &[AttackId]) -> Self {
+        let mut loadout = SkillLoadout::default();
+        if slots.is_empty() {
+            for (slot, attack) in loadout.0.iter_mut().zip(unlocked) {
+                *slot = Some(*attack);
+            }
+        } else {
+            for (slot, attack) in loadout.0.iter_mut().zip(slots) {
+                *slot = attack.filter(|attack| unlocked.contains(attack));
+            }
+        }
+        loadout
+    }
+
+    pub fn contains(&self, attack: AttackId) -> bool {
+        self.0.contains(&Some(attack))
+    }
+
+    /// Puts a skill in a slot. A skill that already sits in another slot swaps places with
+    /// whatever was in the target slot.
+    pub fn set(&mut self, slot: usize, attack: Option<AttackId>) {
+        let previous = self.0[slot];
+        if let Some(other) = attack.and_then(|attack| self.0.iter().position(|equipped| *equipped == Some(attack))) {
+            self.0[other] = previous;
+        }
+        self.0[slot] = attack;
+    }
+
+    /// Puts a newly unlocked skill in the first free slot, if there is one.
+    pub fn add(&mut self, attack: AttackId) {
+        if self.contains(attack) {
+            return;
+        }
+        if let Some(slot) = self.0.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(attack);
+        }
+    }
+}
+
+/// Asks the server to unlock a skill in the skill tree.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct UnlockSkill {
+    pub attack: AttackId,
+}
+
+/// Asks the server to put a skill on a skill key, or to clear the key.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct EquipSkill {
+    pub slot: usize,
+    pub attack: Option<AttackId>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum SkillRejection {
+    NotInTree,
+    AlreadyUnlocked,
+    MissingRequirement,
+    LevelTooLow,
+    NotEnoughPoints,
+    NotUnlocked,
+    InvalidSlot,
+}
+
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct SkillRejected {
+    pub attack: Option<AttackId>,
+    pub reason: SkillRejection,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POTION: ItemId = 1;
+    const SWORD: ItemId = 2;
+
+    fn catalogue() -> ItemCatalogue {
+        let spec = |id, name: &str, max| ItemSpec {
+            id,
+            name: name.to_string(),
+            description: String::new(),
+            max,
+            rarity: Rarity::Common,
+            price: None,
+            icon: None,
+            effect: None,
+        };
+
+        ItemCatalogue(HashMap::from([
+            (POTION, spec(POTION, "Potion", 10)),
+            (SWORD, spec(SWORD, "Sword", 1)),
+        ]))
+    }
+
+    fn inventory(slots: usize) -> Inventory {
+        Inventory { slots: vec![None; slots] }
+    }
+
+    fn stack(id: ItemId, qty: u16) -> ItemStack {
+        ItemStack { id, qty }
+    }
+
+    #[test]
+    fn add_tops_up_existing_stacks_before_using_empty_slots() {
+        let mut inventory = inventory(3);
+        inventory.slots[1] = Some(stack(POTION, 7));
+
+        assert_eq!(inventory.add(stack(POTION, 5), &catalogue()), None);
+        assert_eq!(inventory.slots, vec![Some(stack(POTION, 2)), Some(stack(POTION, 10)), None]);
+    }
+
+    #[test]
+    fn add_splits_over_capacity_stacks_across_slots() {
+        let mut inventory = inventory(4);
+
+        assert_eq!(inventory.add(stack(POTION, 25), &catalogue()), None);
+        assert_eq!(inventory.slots, vec![Some(stack(POTION, 10)), Some(stack(POTION, 10)), Some(stack(POTION, 5)), None]);
+    }
+
+    #[test]
+    fn add_returns_what_does_not_fit() {
+        let mut inventory = inventory(2);
+        inventory.slots[0] = Some(stack(SWORD, 1));
+
+        assert_eq!(inventory.add(stack(POTION, 14), &catalogue()), Some(stack(POTION, 4)));
+        assert_eq!(inventory.add(stack(SWORD, 1), &catalogue()), Some(stack(SWORD, 1)));
+        assert_eq!(inventory.count(POTION), 10);
+    }
+
+    #[test]
+    fn add_never_overflows_a_stack() {
+        let mut inventory = inventory(1);
+        inventory.slots[0] = Some(stack(POTION, 9));
+
+        assert_eq!(inventory.add(stack(POTION, u16::MAX), &catalogue()), Some(stack(POTION, u16::MAX - 1)));
+        assert_eq!(inventory.slots[0], Some(stack(POTION, 10)));
+    }
+
+    #[test]
+    fn unknown_items_do_not_stack() {
+        let mut inventory = inventory(2);
+
+        assert_eq!(inventory.add(stack(99, 3), &catalogue()), Some(stack(99, 1)));
+        assert_eq!(inventory.slots, vec![Some(stack(99, 1)), Some(stack(99, 1))]);
+    }
+
+    #[test]
+    fn remove_takes_from_several_stacks_or_nothing() {
+        let mut inventory = inventory(3);
+        inventory.slots[0] = Some(stack(POTION, 10));
+        inventory.slots[2] = Some(stack(POTION, 3));
+
+        assert_eq!(inventory.remove(POTION, 20), Err(InventoryError::NotEnough));
+        assert_eq!(inventory.count(POTION), 13);
+
+        assert_eq!(inventory.remove(POTION, 5), Ok(()));
+        assert_eq!(inventory.slots, vec![Some(stack(POTION, 8)), None, None]);
+    }
+
+    #[test]
+    fn take_from_slot_clears_emptied_slots() {
+        let mut inventory = inventory(2);
+        inventory.slots[0] = Some(stack(POTION, 4));
+
+        assert_eq!(inventory.take_from_slot(0, 6), Ok(stack(POTION, 4)));
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.take_from_slot(0, 1), Err(InventoryError::EmptySlot));
+        assert_eq!(inventory.take_from_slot(5, 1), Err(InventoryError::InvalidSlot));
+    }
+
+    #[test]
+    fn move_stack_swaps_different_items_and_merges_equal_ones() {
+        let catalogue = catalogue();
+        let mut inventory = inventory(3);
+        inventory.slots[0] = Some(stack(POTION, 6));
+        inventory.slots[1] = Some(stack(SWORD, 1));
+        inventory.slots[2] = Some(stack(POTION, 8));
+
+        assert_eq!(inventory.move_stack(0, 1, &catalogue), Ok(()));
+        assert_eq!(inventory.slots[0], Some(stack(SWORD, 1)));
+        assert_eq!(inventory.slots[1], Some(stack(POTION, 6)));
+
+        assert_eq!(inventory.move_stack(1, 2, &catalogue), Ok(()));
+        assert_eq!(inventory.slots[1], Some(stack(POTION, 4)));
+        assert_eq!(inventory.slots[2], Some(stack(POTION, 10)));
+
+        assert_eq!(inventory.move_stack(0, 3, &catalogue), Err(InventoryError::InvalidSlot));
+    }
+
+    #[test]
+    fn split_moves_part_of_a_stack_to_a_free_slot() {
+        let mut inventory = inventory(3);
+        inventory.slots[1] = Some(stack(POTION, 7));
+
+        assert_eq!(inventory.split(1, 3), Ok(0));
+        assert_eq!(inventory.slots, vec![Some(stack(POTION, 3)), Some(stack(POTION, 4)), None]);
+        assert_eq!(inventory.split(1, 4), Err(InventoryError::NotEnough));
+        assert_eq!(inventory.split(2, 1), Err(InventoryError::EmptySlot));
+    }
+
+    #[test]
+    fn split_needs_a_free_slot() {
+        let mut inventory = inventory(1);
+        inventory.slots[0] = Some(stack(POTION, 7));
+
+        assert_eq!(inventory.split(0, 3), Err(InventoryError::NoFreeSlot));
+        assert_eq!(inventory.slots[0], Some(stack(POTION, 7)));
+    }
+
+    #[test]
+    fn merge_respects_the_stack_size() {
+        let catalogue = catalogue();
+        let mut inventory = inventory(2);
+        inventory.slots[0] = Some(stack(POTION, 6));
+        inventory.slots[1] = Some(stack(POTION, 7));
+
+        assert_eq!(inventory.merge(0, 1, &catalogue), Ok(3));
+        assert_eq!(inventory.slots, vec![Some(stack(POTION, 3)), Some(stack(POTION, 10))]);
+    }
+
+    #[test]
+    fn fits_counts_room_in_stacks_and_empty_slots() {
+        let catalogue = catalogue();
+        let mut inventory = inventory(2);
+        inventory.slots[0] = Some(stack(POTION, 7));
+
+        assert!(inventory.fits(&stack(POTION, 13), &catalogue));
+        assert!(!inventory.fits(&stack(POTION, 14), &catalogue));
+
+        inventory.slots[1] = Some(stack(SWORD, 1));
+        assert!(!inventory.fits(&stack(SWORD, 1), &catalogue));
+    }
+
+    #[test]
+    fn skill_loadout_swaps_a_skill_that_is_already_equipped() {
+        let mut loadout = SkillLoadout([Some(1), Some(2), None, None]);
+
+        loadout.set(2, Some(1));
+        assert_eq!(loadout.0, [None, Some(2), Some(1), None]);
+
+        loadout.set(1, Some(1));
+        assert_eq!(loadout.0, [None, Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn skill_loadout_drops_skills_that_are_not_unlocked() {
+        assert_eq!(SkillLoadout::from_saved(&[Some(1), Some(3), None], &[1, 2]).0, [Some(1), None, None, None]);
+        assert_eq!(SkillLoadout::from_saved(&[], &[1, 2]).0, [Some(1), Some(2), None, None]);
+    }
+
+    #[test]
+    fn stash_keeps_overflow() {
+        let mut stash = Stash::default();
+        stash.add(stack(POTION, 4));
+        stash.add(stack(POTION, 6));
+        stash.add(stack(SWORD, 1));
+
+        assert_eq!(stash.items, vec![stack(POTION, 10), stack(SWORD, 1)]);
+    }
+
+    #[test]
+    fn stash_moves_back_once_there_is_room() {
+        let catalogue = catalogue();
+        let mut inventory = inventory(2);
+        inventory.slots[0] = Some(stack(SWORD, 1));
+        inventory.slots[1] = Some(stack(POTION, 8));
+
+        let mut stash = Stash { items: vec![stack(POTION, 5), stack(SWORD, 1)] };
+        assert!(stash.move_into(&mut inventory, &catalogue));
+        assert_eq!(inventory.slots[1], Some(stack(POTION, 10)));
+        assert_eq!(stash.items, vec![stack(POTION, 3), stack(SWORD, 1)]);
+
+        assert!(!stash.move_into(&mut inventory, &catalogue));
+
+        inventory.slots[0] = None;
+        assert!(stash.move_into(&mut inventory, &catalogue));
+        assert_eq!(inventory.slots[0], Some(stack(POTION, 3)));
+        assert_eq!(stash.items, vec![stack(SWORD, 1)]);
+    }
+}
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::components::player::{EquipmentSlot, ItemId};
 use crate::plugins::attack::AttackId;
 
 #[derive(Resource, Default)]
@@ -10,6 +11,53 @@ pub struct InventoryUIState {
 #[derive(Component)]
 pub struct InventoryPanel;
 
+#[derive(Resource, Default)]
+pub struct SkillTreeUIState {
+    pub open: bool,
+}
+
+#[derive(Component)]
+pub struct SkillTreePanel;
+
+/// Button that asks the server to unlock this skill.
+#[derive(Component)]
+pub struct SkillUnlockButton(pub AttackId);
+
+/// Button that puts an unlocked skill on a skill key, or takes it off when it is already there.
+#[derive(Component)]
+pub struct SkillAssignButton {
+    pub slot: usize,
+    pub attack_id: AttackId,
+}
+
+/// Button for the inventory slot with this index.
+#[derive(Component)]
+pub struct InventorySlot(pub usize);
+
+/// Button for an equipment slot, clicking it takes the item off.
+#[derive(Component)]
+pub struct EquipmentSlotButton(pub EquipmentSlot);
+
+/// Name of the shop whose window is open, if any.
+#[derive(Resource, Default)]
+pub struct ShopUIState {
+    pub open: Option<String>,
+}
+
+#[derive(Component)]
+pub struct ShopPanel;
+
+/// Buys one of this item from the open shop.
+#[derive(Component)]
+pub struct ShopBuyButton(pub ItemId);
+
+/// Sells one item from this inventory slot to the open shop.
+#[derive(Component)]
+pub struct ShopSellButton {
+    pub slot: usize,
+    pub item: ItemId,
+}
+
 #[derive(Component)]
 pub struct HealthText;
 
@@ -25,11 +73,21 @@ pub struct RootUI;
 #[derive(Component)]
 pub struct GoldText;
 
+/// One of the skill keys, shows whatever the `SkillLoadout` has in that slot.
 #[derive(Component)]
 pub struct SkillSlot {
     pub index: usize,
-    pub attack_id: AttackId,
+    pub attack_id: Option<AttackId>,
 }
 
+#[derive(Component)]
+pub struct SkillNameText;
+
 #[derive(Component)]
 pub struct SkillCooldownOverlay;
+
+/// Short message in the middle of the screen that fades away.
+#[derive(Component)]
+pub struct Toast {
+    pub timer: Timer,
+}
@@ -20,6 +20,9 @@ use rand::Rng;
 use noise::{Fbm, NoiseFn};
 use crate::components::island_maps::{Map, IslandMaps, TerrainType};
 use crate::islands::core::{add_boardwalk, reserve_with_margin};
+use crate::plugins::island::spawn_chest;
+use crate::plugins::loot::Loot;
+use crate::plugins::world_state::WorldState;
 
 #[derive(Component)]
 pub struct Atoll;
@@ -28,8 +31,8 @@ pub struct AtollPlugin;
 impl Plugin for AtollPlugin {
     fn build(&self, app: &mut App) {
         app
-        .add_systems(Update, // setup island tiles
-            (generate_island_map.before(setup_island_server), 
+        .add_systems(Update, // setup island tiles, remote clients receive the map from the server instead
+            (generate_island_map.before(setup_island_server).run_if(not(client_connected)), 
             setup_island_server.run_if(server_running))
         );
     }
@@ -38,7 +41,8 @@ impl Plugin for AtollPlugin {
 fn generate_island_map(
     mut commands: Commands,
     mut island_maps: ResMut<IslandMaps>,
-    new_islands: Query<(Entity, &Island), (With<Atoll>, With<GenerateIsland>)>
+    new_islands: Query<(Entity, &Island), (With<Atoll>, With<GenerateIsland>)>,
+    world: Res<WorldState>,
 ) {
     for (entity, island_id) in new_islands.iter() {
         let mut generator = StdRng::seed_from_u64(island_id.0);
@@ -47,6 +51,7 @@ fn generate_island_map(
         if !map.is_some() {
             let mut new_map = Map::new();
             generate_tiles(&mut new_map, island_id.0, &mut generator);
+            world.restore_terrain(island_id.0, &mut new_map);
             island_maps.maps.insert(island_id.0, new_map);
             commands.entity(entity).insert(MapFinishedIsland).remove::<GenerateIsland>();
             println!("Added island to the maps");
@@ -61,6 +66,7 @@ fn setup_island_server(
     mut commands: Commands,
     mut island_maps: ResMut<IslandMaps>,
     islands: Query<(Entity, &Island, Option<&CompletedIslandObjective>), (With<Atoll>, With<MapFinishedIsland>)>,
+    world: Res<WorldState>,
 ) {
     for (entity, island_id, island_obj) in islands.iter() {
 
@@ -81,7 +87,8 @@ fn setup_island_server(
                         Position::new(enemy_pos),
                         MoveTimer(Timer::from_seconds(0.7, TimerMode::Repeating), false),
                         OnIsland(island_id.0),
-                        RangeAggro(8)
+                        RangeAggro(8),
+                        Loot::new("atoll_grunt"),
                     ))
                     .id();
 
@@ -89,6 +96,12 @@ fn setup_island_server(
                 commands.entity(entity).insert(EliminationObjective);
             }
         }
+        else if let Some(state) = world.island(island_id.0) {
+            if let Some(chest_pos) = state.chest {
+                let map = island_maps.maps.get_mut(&island_id.0).unwrap();
+                spawn_chest(&mut commands, map, island_id.0, chest_pos, state.chest_tier);
+            }
+        }
 
         commands.entity(entity).insert(FinishedSetupIsland).remove::<MapFinishedIsland>();
     }
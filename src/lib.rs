@@ -6,29 +6,43 @@ pub mod plugins;
 pub mod islands;
 pub mod attacks;
 
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
 use bevy::render::settings::{Backends, RenderCreation, WgpuSettings};
 use bevy::render::RenderPlugin;
-use plugins::island::IslandPlugin;
-use plugins::network::NetworkPlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::winit::{UpdateMode::Continuous, WinitSettings};
+use plugins::island::{IslandPlugin, IslandPresentationPlugin};
+use plugins::map_sync::MapSyncPlugin;
+use plugins::network::{NetworkPlugin, Transport};
+use plugins::visibility::VisibilityPlugin;
+use plugins::world_state::WorldStatePlugin;
 
 use plugins::camera::CameraPlugin;
 
-use plugins::humanoid::HumanoidPlugin;
-use plugins::enemy::EnemyPlugin;
-use plugins::island_controls::CharacterPlugin;
+use plugins::humanoid::{HumanoidPlugin, HumanoidPresentationPlugin};
+use plugins::enemy::{EnemyPlugin, EnemyPresentationPlugin};
+use plugins::island_controls::{CharacterPlugin, CharacterPresentationPlugin};
 
-use plugins::overworld::OverworldPlugin;
-use plugins::ship::ShipPlugin;
+use plugins::overworld::{OverworldPlugin, OverworldPresentationPlugin};
+use plugins::ship::{ShipPlugin, ShipPresentationPlugin};
 use rand::Rng;
 use crate::components::overworld::WorldSeed;
 use crate::plugins::animations::AnimationsPlugin;
-use crate::plugins::attack::AttackPlugin;
+use crate::plugins::attack::{AttackPlugin, AttackPresentationPlugin};
 use crate::plugins::damage_numbers::DamageNumbersPlugin;
+use crate::plugins::inventory::InventoryPlugin;
+use crate::plugins::items::ItemPlugin;
+use crate::plugins::loot::LootPlugin;
 use crate::plugins::player::PlayerPlugin;
+use crate::plugins::progression::{ProgressionPlugin, ProgressionPresentationPlugin};
+use crate::plugins::shop::{ShopPlugin, ShopPresentationPlugin};
+use crate::plugins::skills::SkillsPlugin;
 use crate::plugins::ui::UIPlugin;
 
 #[derive(States, PartialEq, Eq, Debug, Hash, Clone)]
-enum GameState {
+pub enum GameState {
     Initializing,
     Overworld,
     Island,
@@ -36,6 +50,7 @@ enum GameState {
 
 pub const CHUNK_SIZE : i32 = 16;
 pub const POSITION_SIZE : f32 = 1.0;
+pub const SERVER_TICK_RATE : f64 = 60.0;
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 struct IslandSet;
@@ -43,6 +58,7 @@ struct IslandSet;
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 struct OverworldSet;
 
+/// Windowed game: simulation plus everything needed to render and play it.
 pub struct AppPlugin;
 impl Plugin for AppPlugin {
   fn build(&self, app: &mut App) {
@@ -52,7 +68,46 @@ impl Plugin for AppPlugin {
             ..default()
         }),
         ..default()
-        }))
+        }).set(asset_plugin()))
+        .insert_resource(WinitSettings {
+            focused_mode: Continuous,
+            unfocused_mode: Continuous,
+        })
+        .add_plugins((SimulationPlugin::default(), PresentationPlugin));
+    }
+}
+
+/// Dedicated server: no window, no renderer and no local player.
+pub struct HeadlessPlugin;
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / SERVER_TICK_RATE))),
+            asset_plugin(),
+            StatesPlugin,
+        ))
+        .add_plugins(SimulationPlugin::default());
+    }
+}
+
+/// Data files such as items are hot-reloaded in `dev` builds.
+fn asset_plugin() -> AssetPlugin {
+    AssetPlugin {
+        watch_for_changes_override: Some(cfg!(feature = "dev")),
+        ..default()
+    }
+}
+
+/// Game rules and networking, shared by every peer. Must not touch meshes, materials or input.
+#[derive(Default)]
+pub struct SimulationPlugin {
+    pub transport: Transport,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .insert_resource(self.transport)
         .insert_resource(WorldSeed(rand::rng().random()))
         .insert_state(GameState::Initializing)
         .configure_sets(Update, (
@@ -66,21 +121,45 @@ impl Plugin for AppPlugin {
         ))
         .add_plugins((
             NetworkPlugin,
-            PlayerPlugin,
-            UIPlugin,
-            
+            VisibilityPlugin,
+
             OverworldPlugin,
             ShipPlugin,
 
             IslandPlugin,
+            MapSyncPlugin,
+            WorldStatePlugin,
             CharacterPlugin,
-            EnemyPlugin,    
+            EnemyPlugin,
             HumanoidPlugin,
+            AttackPlugin,
+            (ItemPlugin, InventoryPlugin, LootPlugin, ProgressionPlugin, SkillsPlugin, ShopPlugin),
+        ));
+    }
+}
+
+/// Rendering, UI and input for a player sitting in front of the window.
+pub struct PresentationPlugin;
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            PlayerPlugin,
+            UIPlugin,
+
+            OverworldPresentationPlugin,
+            ShipPresentationPlugin,
+
+            IslandPresentationPlugin,
+            CharacterPresentationPlugin,
+            EnemyPresentationPlugin,
+            HumanoidPresentationPlugin,
             AnimationsPlugin,
 
             CameraPlugin,
             DamageNumbersPlugin,
-            AttackPlugin,
+            AttackPresentationPlugin,
+            ProgressionPresentationPlugin,
+            ShopPresentationPlugin,
         ));
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,34 @@
 use bevy::prelude::*;
+use clap::Parser;
 
-use dice_venture::AppPlugin;
+use dice_venture::{AppPlugin, HeadlessPlugin};
+use dice_venture::plugins::auth::{generate_private_key, issue_token};
+use dice_venture::plugins::network::{Cli, PROTOCOL_ID};
 // use dice_venture::preludes::network_preludes::*;
 // use dice_venture::preludes::humanoid_preludes::*;
 // use dice_venture::components::enemy::{SnakePart, MovementType};
 
 fn main() {
-    App::new()
-    .add_plugins(AppPlugin)
+    let cli = Cli::parse();
+
+    match &cli {
+        Cli::GenerateKey { out } => return generate_private_key(out).unwrap(),
+        Cli::IssueToken(args) => return issue_token(args, PROTOCOL_ID).unwrap(),
+        _ => (),
+    }
+
+    let headless = matches!(cli, Cli::Dedicated(_));
+
+    let mut app = App::new();
+    app.insert_resource(cli);
+
+    if headless {
+        app.add_plugins(HeadlessPlugin);
+    } else {
+        app.add_plugins(AppPlugin);
+    }
+
+    app
     // .add_systems(PreUpdate, update_map.after(ClientSet::Receive))
     // .add_systems(Update, test_function)
     .run();
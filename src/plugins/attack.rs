@@ -1,17 +1,24 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
 use bevy::ecs::entity::MapEntities;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::error::Error;
 use crate::attacks::base_attack::BaseAttackPlugin;
-use crate::attacks::counter::CounterPlugin;
+use crate::attacks::counter::{CounterPlugin, CounterPresentationPlugin};
 use crate::attacks::cut_through::CutThroughPlugin;
 use crate::attacks::dagger_throw::DaggerThrowPlugin;
-use crate::components::enemy::Enemy;
-use crate::components::humanoid::{ActiveSkills, AttackCooldowns, DamageVisualizer, Health, Stunned, ViewDirection, VisualEntity, VisualRef};
+use crate::attacks::script::{AttackScriptPlugin, AttackStep, ScriptedAttack};
+use crate::components::character::{CombatStats, Loadout};
+use crate::components::enemy::STANDARD;
+use crate::components::humanoid::{ActionState, ActiveSkills, AttackCooldowns, AttackWindow, DamageVisualizer, Health, Status, StatusEffect, StatusFlags, Stunned, ViewDirection, VisualEntity, VisualRef};
 use crate::components::island::OnIsland;
-use crate::components::island_maps::IslandMaps;
-use crate::components::player::RewardEvent;
 use crate::plugins::damage_numbers::SpawnNumberEvent;
-use crate::plugins::projectiles::ProjectilePlugin;
+use crate::plugins::lag_compensation::{CasterLag, HitTargets, PositionHistory, MAX_REWIND_TICKS};
+use crate::plugins::loot::DamageContributions;
+use crate::plugins::network::OwnedBy;
+use crate::plugins::projectiles::{ProjectilePlugin, ProjectilePresentationPlugin};
+use crate::plugins::visibility::IslandOccupants;
 use crate::preludes::network_preludes::*;
 use crate::CHUNK_SIZE;
 use std::collections::HashMap;
@@ -23,15 +30,22 @@ pub type AttackId = u64;
 
 /// Unique key per attack
 pub fn key_of<T: 'static>() -> AttackId {
+    key_of_name(std::any::type_name::<T>())
+}
+
+/// Key of an attack that only exists as a script in the attack file
+pub fn key_of_name(name: &str) -> AttackId {
     let mut hash = BuildHasherDefault::<XxHash64>::default().build_hasher();
-    hash.write(std::any::type_name::<T>().as_bytes());
+    hash.write(name.as_bytes());
     hash.finish()
 }
 
 #[derive(Debug, Deserialize, Event, Serialize)]
 pub struct ClientAttack {
    pub attack_id: AttackId,
-    pub offset: IVec3
+    pub offset: IVec3,
+    /// Last server tick the client had received, hits are resolved against the world at that tick
+    pub seen_tick: u32,
 }
 
 #[derive(Debug, Deserialize, Event, Serialize)]
@@ -40,6 +54,28 @@ pub struct AttackInfo{
     pub offset: IVec3
 }
 
+/// Why the server refused to run an attack a client asked for.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum AttackRejection {
+    NotOwner,
+    Unknown,
+    NotInLoadout,
+    InvalidDirection,
+    OnCooldown,
+    Busy,
+    Stunned,
+}
+
+/// Sent to the caster when its attack was refused, so it can undo what it predicted.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct AttackRejected {
+    pub attack_id: AttackId,
+    pub reason: AttackRejection,
+}
+
+// Cooldowns are started on the client first, a little slack keeps latency jitter from rejecting fair attacks
+const COOLDOWN_TOLERANCE: f32 = 0.1;
+
 #[derive(Component)]
 pub struct AttackMarker;
 
@@ -48,41 +84,145 @@ pub type SpawnFunction = fn(&mut Commands, Entity, IVec3);
 #[derive(Resource, Default)]
 pub struct AttackRegistry {
     map: HashMap<AttackId, SpawnFunction>,
+    names: HashMap<String, AttackId>,
+    scripts: HashSet<AttackId>,
 }
 
 impl AttackRegistry {
     pub fn register<T: 'static>(&mut self, func: SpawnFunction) -> AttackId {
         let key = key_of::<T>();
         self.map.insert(key, func);
+
+        let name = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+        self.names.insert(name.to_string(), key);
+        key
+    }
+
+    /// Registers an attack that is made of script steps instead of its own component and systems.
+    pub fn register_script(&mut self, name: &str) -> AttackId {
+        let key = key_of_name(name);
+        self.names.insert(name.to_string(), key);
+        self.scripts.insert(key);
         key
     }
+
+    /// Looks up an attack by its type name, how data files refer to attacks.
+    pub fn id_of(&self, name: &str) -> Option<AttackId> {
+        self.names.get(name).copied()
+    }
     
     pub fn spawn(&self, key: AttackId, commands: &mut Commands, entity: Entity, offset: IVec3) {
         if let Some(func) = self.map.get(&key) { 
             func(commands, entity, offset) 
         }
+        else if self.scripts.contains(&key) {
+            if let Ok(mut ec) = commands.get_entity(entity) {
+                ec.insert(ScriptedAttack::new(key, offset));
+            }
+        }
         else { error!("Unknown attack key {key}") }
     }
 }
 
-#[derive(Clone)]
+const ATTACK_FILE: &str = "attacks.spec.ron";
+
+/// Balancing of one attack, from `assets/attacks.spec.ron`. Times are in seconds.
+#[derive(Clone, Debug, Deserialize)]
 pub struct AttackSpec {
-    pub offsets : &'static[IVec3],
-    pub cooldown : f32,
-    pub damage: u64
+    /// Where a target has to stand, relative to the attacker, for an enemy to use the attack
+    #[serde(default = "standard_offsets")]
+    pub offsets: Vec<IVec3>,
+    pub cooldown: f32,
+    pub damage: u64,
+    /// Time before the attack lands
+    #[serde(default)]
+    pub windup: f32,
+    /// Time the attacker stays busy after the attack landed
+    #[serde(default)]
+    pub active: f32,
+    /// Tiles the attack reaches
+    #[serde(default = "one_tile")]
+    pub range: u8,
+    /// Put on whatever the attack hits
+    #[serde(default)]
+    pub on_hit: Vec<StatusEffect>,
+    /// Steps of an attack that has no component of its own
+    #[serde(default)]
+    pub script: Vec<AttackStep>,
+}
+
+fn standard_offsets() -> Vec<IVec3> {
+    STANDARD.to_vec()
+}
+
+fn one_tile() -> u8 {
+    1
 }
 
+impl AttackSpec {
+    pub fn duration(&self) -> f32 {
+        self.windup + self.active
+    }
+
+    /// Least time the attacker stays busy, the timed steps of a script included.
+    pub fn busy_time(&self) -> f32 {
+        let script: f32 = self.script.iter().map(|step| match step {
+            AttackStep::Windup(seconds) | AttackStep::Wait(seconds) | AttackStep::NegateDamage(seconds) => *seconds,
+            _ => 0.0,
+        }).sum();
+        self.duration() + script
+    }
+}
+
+/// Attack specs by attack id, filled from `assets/attacks.spec.ron` once every attack has registered.
+/// Scripted attacks are registered from the file itself.
 #[derive(Resource, Default)]
 pub struct AttackCatalogue(pub HashMap<AttackId, AttackSpec>);
 
+/// `assets/attacks.spec.ron`, attack specs keyed by the attack's name in the `AttackRegistry`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct AttackSpecFile(pub HashMap<String, AttackSpec>);
+
+#[derive(Default)]
+struct AttackSpecLoader;
+
+impl AssetLoader for AttackSpecLoader {
+    type Asset = AttackSpecFile;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<AttackSpecFile, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spec.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct AttackSpecHandle(Handle<AttackSpecFile>);
+
 pub struct AttackPlugin;
 impl Plugin for AttackPlugin {
     fn build(&self, app: &mut App) {
         app
         .insert_resource(AttackRegistry::default())
         .insert_resource(AttackCatalogue::default())
+        .init_asset::<AttackSpecFile>()
+        .init_asset_loader::<AttackSpecLoader>()
+        .init_resource::<PositionHistory>()
         .add_client_trigger::<ClientAttack>(Channel::Unordered)
         .add_server_trigger::<AttackInfo>(Channel::Unordered)
+        .add_server_trigger::<AttackRejected>(Channel::Ordered)
         .add_server_trigger::<ClientDamageEvent>(Channel::Unordered)
         .add_mapped_server_trigger::<NegateDamageTrigger>(Channel::Unordered)
         .add_event::<NegatedDamageEvent>()
@@ -92,8 +232,56 @@ impl Plugin for AttackPlugin {
         .add_observer(damage_trigger)
         .add_observer(attack_trigger)
         .add_observer(damage_negated_trigger)
-        .add_systems(PreUpdate, (tick_attack_cooldowns, interrupt_attack_stun, damage_visualizer_system))
-        .add_plugins((BaseAttackPlugin, CutThroughPlugin, DaggerThrowPlugin, CounterPlugin, ProjectilePlugin));
+        .add_observer(client_attack_rejected)
+        .add_systems(Startup, load_attack_specs)
+        .add_systems(PreUpdate, (update_attack_catalogue, tick_attack_cooldowns, interrupt_attack_stun))
+        .add_plugins((BaseAttackPlugin, CutThroughPlugin, DaggerThrowPlugin, CounterPlugin, AttackScriptPlugin, ProjectilePlugin));
+    }
+}
+
+pub struct AttackPresentationPlugin;
+impl Plugin for AttackPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(PreUpdate, damage_visualizer_system)
+        .add_plugins((CounterPresentationPlugin, ProjectilePresentationPlugin));
+    }
+}
+
+fn load_attack_specs(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(AttackSpecHandle(assets.load(ATTACK_FILE)));
+}
+
+// Attacks register themselves on startup, so names can be resolved by the time the file has loaded
+fn update_attack_catalogue(
+    mut events: EventReader<AssetEvent<AttackSpecFile>>,
+    handle: Res<AttackSpecHandle>,
+    files: Res<Assets<AttackSpecFile>>,
+    mut registry: ResMut<AttackRegistry>,
+    mut catalogue: ResMut<AttackCatalogue>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&handle.0) && !event.is_modified(&handle.0) {
+            continue;
+        }
+        let Some(file) = files.get(&handle.0) else { continue };
+
+        catalogue.0.clear();
+        for (name, spec) in file.0.iter() {
+            let id = match registry.id_of(name) {
+                None if !spec.script.is_empty() => Some(registry.register_script(name)),
+                id => id,
+            };
+            match id {
+                Some(id) => { catalogue.0.insert(id, spec.clone()); }
+                None => warn!("Attack file lists unknown attack '{name}'"),
+            }
+        }
+
+        info!("Loaded {} attack specs", catalogue.0.len());
     }
 }
 
@@ -127,20 +315,89 @@ fn damage_negated_trigger(
     }
 }
 
+fn validate_attack(
+    client_entity: Entity,
+    attack: &ClientAttack,
+    caster: (&OwnedBy, &Loadout, &ActionState, &StatusFlags, &AttackCooldowns, &AttackWindow),
+    catalogue: &AttackCatalogue,
+) -> Result<(), AttackRejection> {
+    let (owner, loadout, action_state, status, cooldowns, window) = caster;
+
+    if owner.0 != client_entity {
+        return Err(AttackRejection::NotOwner);
+    }
+    if !catalogue.0.contains_key(&attack.attack_id) {
+        return Err(AttackRejection::Unknown);
+    }
+    if !loadout.0.contains(&attack.attack_id) {
+        return Err(AttackRejection::NotInLoadout);
+    }
+    if attack.offset.y != 0 || attack.offset.abs().element_sum() != 1 {
+        return Err(AttackRejection::InvalidDirection);
+    }
+    if status.0.contains(Status::STUNNED) || *action_state == ActionState::Stunned {
+        return Err(AttackRejection::Stunned);
+    }
+    // The client starts its next attack when its own copy ends, about one latency before the server's does
+    if *action_state == ActionState::Attacking && window.0.remaining_secs() > COOLDOWN_TOLERANCE {
+        return Err(AttackRejection::Busy);
+    }
+    if let Some(timer) = cooldowns.0.get(&attack.attack_id) {
+        if timer.remaining_secs() > COOLDOWN_TOLERANCE {
+            return Err(AttackRejection::OnCooldown);
+        }
+    }
+
+    Ok(())
+}
+
 fn server_apply_attack(
     client_trigger: Trigger<FromClient<ClientAttack>>,
     mut commands: Commands,
+    mut casters: Query<(&OnIsland, &OwnedBy, &Loadout, &ActionState, &StatusFlags, &mut AttackCooldowns, &mut AttackWindow)>,
+    catalogue: Res<AttackCatalogue>,
+    occupants: Res<IslandOccupants>,
+    server_tick: Res<ServerTick>,
+    stats_query: Query<&CombatStats>,
 ) {
     let msg = client_trigger.event();
     let attacker = client_trigger.target();
+    let Ok((island, owner, loadout, action_state, status, mut cooldowns, mut window)) = casters.get_mut(attacker) else {
+        return;
+    };
+
+    // The host runs attack_trigger with server authority already
+    if client_trigger.client_entity != SERVER {
+        if let Err(reason) = validate_attack(client_trigger.client_entity, msg, (owner, loadout, action_state, status, &*cooldowns, &*window), &catalogue) {
+            debug!("Rejected attack {} from {:?}: {:?}", msg.attack_id, client_trigger.client_entity, reason);
+            commands.server_trigger_targets(
+                ToClients {
+                    mode: SendMode::Direct(client_trigger.client_entity),
+                    event: AttackRejected { attack_id: msg.attack_id, reason },
+                },
+                attacker,
+            );
+            return;
+        }
 
-    commands.server_trigger_targets(
-        ToClients {
-            mode : SendMode::BroadcastExcept(client_trigger.client_entity),
-            event: AttackInfo { attack_id: msg.attack_id, offset: msg.offset },
-        },
-        attacker,
-    );
+        let stats = stats_query.get(attacker).copied().unwrap_or_default();
+        let spec = &catalogue.0[&msg.attack_id];
+        cooldowns.0.insert(msg.attack_id, Timer::from_seconds(stats.scale_cooldown(spec.cooldown), TimerMode::Once));
+        window.0 = Timer::from_seconds(spec.busy_time(), TimerMode::Once);
+
+        let lag = server_tick.get().saturating_sub(msg.seen_tick).min(MAX_REWIND_TICKS);
+        commands.entity(attacker).insert(CasterLag(lag));
+    }
+
+    for client in occupants.with_server(island.0).filter(|client| *client != client_trigger.client_entity) {
+        commands.server_trigger_targets(
+            ToClients {
+                mode : SendMode::Direct(client),
+                event: AttackInfo { attack_id: msg.attack_id, offset: msg.offset },
+            },
+            attacker,
+        );
+    }
 }
 
 fn client_visualize_attack(
@@ -166,8 +423,39 @@ fn client_visualize_attack(
     }
 }
 
+fn client_attack_rejected(
+    trigger: Trigger<AttackRejected>,
+    mut commands: Commands,
+    mut casters: Query<(&mut AttackCooldowns, &mut ActiveSkills, &mut ActionState, Option<&VisualRef>)>,
+    mut visuals: Query<&mut Transform, With<VisualEntity>>,
+) {
+    warn!("Attack {} was rejected by the server: {:?}", trigger.attack_id, trigger.reason);
+
+    let Ok((mut cooldowns, mut active_skills, mut action_state, visual_ref)) = casters.get_mut(trigger.target()) else {
+        return;
+    };
+
+    cooldowns.0.remove(&trigger.attack_id);
+
+    if let Some(skill_entity) = active_skills.0.remove(&trigger.attack_id) {
+        if let Ok(mut entity_commands) = commands.get_entity(skill_entity) {
+            entity_commands.despawn();
+        }
+    }
+
+    if *action_state == ActionState::Attacking {
+        *action_state = ActionState::Idle;
+    }
+
+    // Attack animations push the visual away from the character, put it back
+    if let Some(mut transform) = visual_ref.and_then(|visual| visuals.get_mut(visual.0).ok()) {
+        transform.translation = Vec3::ZERO;
+    }
+}
+
 fn tick_attack_cooldowns(
     mut cooldowns: Query<&mut AttackCooldowns>,
+    mut windows: Query<&mut AttackWindow>,
     time: Res<Time>,
 ) {
     for mut cooldown in &mut cooldowns {
@@ -175,6 +463,9 @@ fn tick_attack_cooldowns(
             timer.tick(time.delta());
         }
     }
+    for mut window in &mut windows {
+        window.0.tick(time.delta());
+    }
 }
 
 #[derive(Event)]
@@ -182,12 +473,14 @@ pub struct DamageEvent {
     pub owner: Entity,
     pub island: u64,
     pub offset: IVec3,
-    pub damage: u64
+    pub damage: u64,
+    /// The attack that hit, its on-hit effects are applied to the victim
+    pub attack_id: AttackId,
 }
 
 impl DamageEvent {
-    pub fn new(owner: Entity, island: u64, offset: IVec3, damage: u64) -> Self {
-        Self { owner, island, offset, damage }
+    pub fn new(owner: Entity, island: u64, offset: IVec3, damage: u64, attack_id: AttackId) -> Self {
+        Self { owner, island, offset, damage, attack_id }
     }
 }
 
@@ -210,7 +503,6 @@ pub struct ClientDamageEvent {
 fn client_damage_trigger(
     damage_trigger: Trigger<ClientDamageEvent>,
     mut commands: Commands,
-    enemies: Query<Entity, With<Enemy>>
 ){
     commands.trigger(SpawnNumberEvent {amount: damage_trigger.amount, position: damage_trigger.position, entity: damage_trigger.target()} );
 
@@ -220,62 +512,71 @@ fn client_damage_trigger(
         original_color: None,
     });
 
-    if damage_trigger.remaining_health == 0 {
-        if let Ok(_enemy_entity) = enemies.get(damage_trigger.target()) {
-            // TODO: get the type of the enemy and its possible loot, xp and gold
-            commands.trigger(RewardEvent {
-                items: None,
-                xp: 1,
-                gold: 0,
-            });
-        }
-    }   
 }
 
 fn damage_trigger(
     damage_trigger: Trigger<DamageEvent>,
-    island_maps: Res<IslandMaps>,
+    targets: HitTargets,
+    stats_query: Query<&CombatStats>,
+    owners: Query<&OwnedBy>,
+    mut contributions: Query<&mut DamageContributions>,
     mut health: Query<(&mut Health, Option<&Children>)>,
     negate_query: Query<&NegatingDamage>,
-    server: Option<Res<RenetServer>>,
+    catalogue: Res<AttackCatalogue>,
+    server: Res<RepliconServer>,
+    occupants: Res<IslandOccupants>,
     mut commands: Commands
 ) {
-    if server.is_some() {
-        if let Some(map) = island_maps.maps.get(&damage_trigger.island) {
-            if let Some(victim) = map.get_target(damage_trigger.offset) {
-                if let Ok((mut hp, children)) = health.get_mut(victim) {
-                    println!("Victim found");
-                    let mut negated = false;
-
-                    if let Some(children) = children { // checking if there is any ability negating the attack
-                        for child in children.iter() {
-                            if let Ok(negate_instance) = negate_query.get(child) {
-
-                                negated = true;
+    if server.is_running() {
+        // Enemies have no stats, their attacks hit for the listed damage
+        let damage = stats_query.get(damage_trigger.owner).map_or(damage_trigger.damage, |stats| stats.scale_damage(damage_trigger.damage));
+
+        if let Some(victim) = targets.target(damage_trigger.owner, damage_trigger.island, damage_trigger.offset) {
+            if let Ok((mut hp, children)) = health.get_mut(victim) {
+                let mut negated = false;
+
+                if let Some(children) = children { // checking if there is any ability negating the attack
+                    for child in children.iter() {
+                        if let Ok(negate_instance) = negate_query.get(child) {
+
+                            negated = true;
+                            for client in occupants.with_server(damage_trigger.island) {
                                 commands.server_trigger(ToClients { 
-                                    mode: SendMode::Broadcast, 
+                                    mode: SendMode::Direct(client), 
                                     event: NegateDamageTrigger {
                                         attack_id: negate_instance.0,
                                         owner: damage_trigger.owner,
                                         victim: victim,
                                         island: damage_trigger.island,
                                         offset: damage_trigger.offset,
-                                        damage: damage_trigger.damage,
+                                        damage,
                                     }},
                                 );
-                                break;
                             }
+                            break;
+                        }
+                    }
+                }
+                
+                if !negated {
+                    let dealt = damage.min(hp.get());
+                    let remaining_health = hp.damage(damage);
+
+                    if let (Ok(mut contributions), Ok(attacker)) = (contributions.get_mut(victim), owners.get(damage_trigger.owner)) {
+                        contributions.record(attacker.0, dealt);
+                    }
+                    if let Some(spec) = catalogue.0.get(&damage_trigger.attack_id) {
+                        for effect in spec.on_hit.iter() {
+                            effect.apply(&mut commands, victim);
                         }
                     }
-                    
-                    if !negated {
-                        let remaining_health = hp.damage(damage_trigger.damage);
-                        println!("doing the damage: {}", remaining_health);
+                    debug!("{:?} hit {:?} for {}, {} health left", damage_trigger.owner, victim, damage, remaining_health);
+                    for client in occupants.clients(damage_trigger.island) {
                         commands.server_trigger_targets(
                             ToClients {
-                                mode: SendMode::Broadcast,
+                                mode: SendMode::Direct(*client),
                                 event: ClientDamageEvent {
-                                    amount: damage_trigger.damage,
+                                    amount: damage,
                                     position: damage_trigger.offset,
                                     remaining_health,
                                 },
@@ -283,8 +584,8 @@ fn damage_trigger(
                             victim,
                         );
                     }
-                    
                 }
+                
             }
         }
     }
@@ -310,9 +611,16 @@ fn attack_trigger(
     mut commands: Commands,
     attack_reg: Res<AttackRegistry>,
     attack_cat: Res<AttackCatalogue>,
+    update_tick: Res<ServerUpdateTick>,
     mut cooldowns_query: Query<&mut AttackCooldowns>,
     mut active_skills_q: Query<&mut ActiveSkills>,
+    stats_query: Query<&CombatStats>,
 ) {
+    // Nothing can be cast before the attack file has loaded
+    let Some(spec) = attack_cat.0.get(&attack_trigger.attack_id) else {
+        return;
+    };
+
     if let Ok(cooldowns) = &mut cooldowns_query.get_mut(attack_trigger.entity) {
         if let Some(timer) = cooldowns.0.get_mut(&attack_trigger.attack_id) {
             if !timer.finished() { 
@@ -320,7 +628,9 @@ fn attack_trigger(
             }
         }
 
-        cooldowns.0.insert(attack_trigger.attack_id, Timer::from_seconds(attack_cat.0.get(&attack_trigger.attack_id).unwrap().cooldown, TimerMode::Once));
+        let stats = stats_query.get(attack_trigger.entity).copied().unwrap_or_default();
+        let cooldown = stats.scale_cooldown(spec.cooldown);
+        cooldowns.0.insert(attack_trigger.attack_id, Timer::from_seconds(cooldown, TimerMode::Once));
     }
 
     if let Ok(mut active_skills) = active_skills_q.get_mut(attack_trigger.entity) {
@@ -334,7 +644,8 @@ fn attack_trigger(
         commands.client_trigger_targets(
             ClientAttack {
                 attack_id: attack_trigger.attack_id,
-                offset: attack_trigger.offset
+                offset: attack_trigger.offset,
+                seen_tick: update_tick.get(),
             },
             attack_trigger.entity
         );
@@ -398,4 +709,98 @@ fn damage_visualizer_system(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLASH: AttackId = 1;
+    const KICK: AttackId = 2;
+
+    fn catalogue() -> AttackCatalogue {
+        let spec: AttackSpec = ron::from_str("(cooldown: 1.0, damage: 5)").unwrap();
+        AttackCatalogue(HashMap::from([(SLASH, spec.clone()), (KICK, spec)]))
+    }
+
+    fn attack(attack_id: AttackId, offset: IVec3) -> ClientAttack {
+        ClientAttack { attack_id, offset, seen_tick: 0 }
+    }
+
+    /// Validates as client 1 for a caster owned by client 1 with only `SLASH` in its loadout.
+    fn validate(attack: &ClientAttack, action_state: ActionState, status: Status, cooldowns: &AttackCooldowns) -> Result<(), AttackRejection> {
+        validate_during(attack, action_state, status, cooldowns, &AttackWindow::default())
+    }
+
+    fn validate_during(attack: &ClientAttack, action_state: ActionState, status: Status, cooldowns: &AttackCooldowns, window: &AttackWindow) -> Result<(), AttackRejection> {
+        let client = Entity::from_raw(1);
+        let caster = (&OwnedBy(client), &Loadout(vec![SLASH]), &action_state, &StatusFlags(status), cooldowns, window);
+        validate_attack(client, attack, caster, &catalogue())
+    }
+
+    #[test]
+    fn accepts_a_fair_attack() {
+        assert_eq!(validate(&attack(SLASH, IVec3::X), ActionState::Idle, Status::empty(), &AttackCooldowns::default()), Ok(()));
+        assert_eq!(validate(&attack(SLASH, IVec3::NEG_Z), ActionState::Moving, Status::ROOTED, &AttackCooldowns::default()), Ok(()));
+    }
+
+    #[test]
+    fn rejects_attacks_for_someone_elses_caster() {
+        let caster = (&OwnedBy(Entity::from_raw(2)), &Loadout(vec![SLASH]), &ActionState::Idle, &StatusFlags::default(), &AttackCooldowns::default(), &AttackWindow::default());
+        assert_eq!(validate_attack(Entity::from_raw(1), &attack(SLASH, IVec3::X), caster, &catalogue()), Err(AttackRejection::NotOwner));
+    }
+
+    #[test]
+    fn rejects_unknown_and_unequipped_attacks() {
+        let cooldowns = AttackCooldowns::default();
+        assert_eq!(validate(&attack(99, IVec3::X), ActionState::Idle, Status::empty(), &cooldowns), Err(AttackRejection::Unknown));
+        assert_eq!(validate(&attack(KICK, IVec3::X), ActionState::Idle, Status::empty(), &cooldowns), Err(AttackRejection::NotInLoadout));
+    }
+
+    #[test]
+    fn rejects_anything_but_one_horizontal_step() {
+        let cooldowns = AttackCooldowns::default();
+        for offset in [IVec3::ZERO, IVec3::Y, IVec3::new(1, 0, 1), IVec3::new(2, 0, 0)] {
+            assert_eq!(validate(&attack(SLASH, offset), ActionState::Idle, Status::empty(), &cooldowns), Err(AttackRejection::InvalidDirection));
+        }
+    }
+
+    #[test]
+    fn rejects_stunned_and_busy_casters() {
+        let cooldowns = AttackCooldowns::default();
+        assert_eq!(validate(&attack(SLASH, IVec3::X), ActionState::Idle, Status::STUNNED, &cooldowns), Err(AttackRejection::Stunned));
+        assert_eq!(validate(&attack(SLASH, IVec3::X), ActionState::Stunned, Status::empty(), &cooldowns), Err(AttackRejection::Stunned));
+    }
+
+    #[test]
+    fn busy_casters_get_the_same_latency_allowance() {
+        let cooldowns = AttackCooldowns::default();
+        let mut window = AttackWindow(Timer::from_seconds(0.5, TimerMode::Once));
+        assert_eq!(validate_during(&attack(SLASH, IVec3::X), ActionState::Attacking, Status::empty(), &cooldowns, &window), Err(AttackRejection::Busy));
+
+        window.0.tick(std::time::Duration::from_secs_f32(0.5 - COOLDOWN_TOLERANCE * 2.0));
+        assert_eq!(validate_during(&attack(SLASH, IVec3::X), ActionState::Attacking, Status::empty(), &cooldowns, &window), Err(AttackRejection::Busy));
+
+        window.0.tick(std::time::Duration::from_secs_f32(COOLDOWN_TOLERANCE * 1.5));
+        assert_eq!(validate_during(&attack(SLASH, IVec3::X), ActionState::Attacking, Status::empty(), &cooldowns, &window), Ok(()));
+    }
+
+    #[test]
+    fn busy_time_counts_timed_script_steps() {
+        let spec: AttackSpec = ron::from_str("(cooldown: 1.0, damage: 5, windup: 0.1, active: 0.2)").unwrap();
+        assert!((spec.busy_time() - 0.3).abs() < 1e-6);
+
+        let spec: AttackSpec = ron::from_str("(cooldown: 1.0, damage: 5, script: [Windup(0.3), Area([]), Wait(0.2)])").unwrap();
+        assert!((spec.busy_time() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cooldowns_allow_for_a_little_latency() {
+        let mut cooldowns = AttackCooldowns::default();
+        cooldowns.0.insert(SLASH, Timer::from_seconds(1.0, TimerMode::Once));
+        assert_eq!(validate(&attack(SLASH, IVec3::X), ActionState::Idle, Status::empty(), &cooldowns), Err(AttackRejection::OnCooldown));
+
+        cooldowns.0.get_mut(&SLASH).unwrap().tick(std::time::Duration::from_secs_f32(1.0 - COOLDOWN_TOLERANCE / 2.0));
+        assert_eq!(validate(&attack(SLASH, IVec3::X), ActionState::Idle, Status::empty(), &cooldowns), Ok(()));
+    }
+}
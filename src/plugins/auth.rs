@@ -0,0 +1,81 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bevy_replicon_renet2::netcode::{ConnectToken, NETCODE_KEY_BYTES};
+use clap::Args;
+
+pub type PrivateKey = [u8; NETCODE_KEY_BYTES];
+
+const TOKEN_EXPIRE_SECONDS: u64 = 60 * 60 * 24;
+const TOKEN_TIMEOUT_SECONDS: i32 = 15;
+
+#[derive(Args, PartialEq, Clone)]
+pub struct IssueTokenArgs {
+    /// Private key file shared with the server
+    #[arg(short, long)]
+    pub key: PathBuf,
+
+    /// Server address written into the token, can be given multiple times
+    #[arg(short, long = "server-addr", required = true)]
+    pub server_addr: Vec<SocketAddr>,
+
+    /// Id of the client the token is for, the client prints it on startup
+    #[arg(short, long)]
+    pub client_id: Option<u64>,
+
+    #[arg(short, long, default_value_t = TOKEN_EXPIRE_SECONDS)]
+    pub expire_seconds: u64,
+
+    #[arg(short, long, default_value = "connect_token.bin")]
+    pub out: PathBuf,
+}
+
+pub fn generate_private_key(path: &Path) -> Result<(), Box<dyn Error>> {
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()).into());
+    }
+
+    let key: PrivateKey = rand::random();
+    fs::write(path, key)?;
+    println!("Wrote private key to {}", path.display());
+    Ok(())
+}
+
+pub fn load_private_key(path: &Path) -> Result<PrivateKey, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        Box::<dyn Error>::from(format!("{} holds {} bytes, expected a {NETCODE_KEY_BYTES} byte key", path.display(), bytes.len()))
+    })
+}
+
+pub fn issue_token(args: &IssueTokenArgs, protocol_id: u64) -> Result<(), Box<dyn Error>> {
+    let private_key = load_private_key(&args.key)?;
+    let client_id = args.client_id.unwrap_or_else(rand::random);
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+
+    let token = ConnectToken::generate(
+        current_time,
+        protocol_id,
+        args.expire_seconds,
+        client_id,
+        TOKEN_TIMEOUT_SECONDS,
+        0,
+        args.server_addr.clone(),
+        None,
+        &private_key,
+    )?;
+
+    let mut writer = BufWriter::new(File::create(&args.out)?);
+    token.write(&mut writer)?;
+    println!("Wrote connect token for client {client_id} to {}", args.out.display());
+    Ok(())
+}
+
+pub fn load_token(path: &Path) -> Result<ConnectToken, Box<dyn Error>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Ok(ConnectToken::read(&mut reader)?)
+}
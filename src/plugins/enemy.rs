@@ -7,6 +7,8 @@ use crate::components::overworld::{LocalIsland, Island};
 use crate::plugins::attack::{AttackCatalogue, AttackInfo};
 use crate::plugins::enemy_behaviour::AggressionPlugin;
 use crate::plugins::enemy_movement::MovementPlugin;
+use crate::plugins::loot::{DamageContributions, Loot, LootDropped};
+use crate::plugins::visibility::IslandOccupants;
 use crate::preludes::network_preludes::*;
 use crate::preludes::humanoid_preludes::*;
 use crate::components::enemy::{Attacks, SnakePart};
@@ -20,10 +22,15 @@ impl Plugin for EnemyPlugin {
         .replicate::<Enemy>()
         .replicate::<Shape>()
         .replicate::<SnakePart>()
-        .add_systems(PreUpdate,
-            ((init_enemy).in_set(IslandSet),
-            (attack_check, enemy_death_check).run_if(server_running))
-        );
+        .add_systems(PreUpdate, (attack_check, enemy_death_check).run_if(server_running));
+    }
+}
+
+pub struct EnemyPresentationPlugin;
+impl Plugin for EnemyPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(PreUpdate, (init_enemy).in_set(IslandSet));
     }
 }
 
@@ -117,12 +124,13 @@ fn init_enemy(
 //TODO add system to easily add new attacks to enemies, probably at the enemy rules?
 fn attack_check(
     mut commands: Commands,
-    mut enemies: Query<(Entity, &Position, &mut AttackCooldowns, &Attacks, &ActionState), With<Enemy>>,
+    mut enemies: Query<(Entity, &Position, &mut AttackCooldowns, &Attacks, &ActionState, &OnIsland), With<Enemy>>,
     players: Query<(Entity, &Position), With<Character>>,
     catalog: Res<AttackCatalogue>,
+    occupants: Res<IslandOccupants>,
     mut view_direction_q: Query<&mut ViewDirection>
 ) {
-    for (enemy_entity, enemy_pos, mut cooldowns, attacks, action_state) in &mut enemies {
+    for (enemy_entity, enemy_pos, mut cooldowns, attacks, action_state, island) in &mut enemies {
         // iterate over all attacks this enemy can use
         if *action_state != ActionState::Idle {
             continue;
@@ -135,7 +143,7 @@ fn attack_check(
                 }
             }
 
-            let spec = catalog.0.get(id).unwrap();
+            let Some(spec) = catalog.0.get(id) else { continue };
 
             if let Some((_, target_pos)) = players.iter().find(|(_, pos)| spec.offsets.contains(&(pos.0 - enemy_pos.0))) {
                 let dir = target_pos.0 - enemy_pos.0;
@@ -146,13 +154,15 @@ fn attack_check(
             
                 cooldowns.0.insert(*id, Timer::from_seconds(spec.cooldown, TimerMode::Once));
 
-                commands.server_trigger_targets(
-                    ToClients {
-                        mode  : SendMode::Broadcast,
-                        event : AttackInfo { attack_id: *id, offset: dir },
-                    },
-                    enemy_entity,
-                );
+                for client in occupants.with_server(island.0) {
+                    commands.server_trigger_targets(
+                        ToClients {
+                            mode  : SendMode::Direct(client),
+                            event : AttackInfo { attack_id: *id, offset: dir },
+                        },
+                        enemy_entity,
+                    );
+                }
 
                 break;
             }
@@ -162,13 +172,18 @@ fn attack_check(
 
 fn enemy_death_check(
     mut commands: Commands,
-    entities: Query<(&OnIsland, &Health, Entity), With<Enemy>>,
+    entities: Query<(&OnIsland, &Health, Entity, Option<(&Loot, &DamageContributions)>), (With<Enemy>, Without<RemoveEntity>)>,
     snake_parts: Query<&SnakePart>,
-    mut island_maps: ResMut<IslandMaps>
+    mut island_maps: ResMut<IslandMaps>,
+    mut loot_dropped: EventWriter<LootDropped>,
 ) {
-    for (island, health, entity) in &entities {
+    for (island, health, entity, loot) in &entities {
         if health.get() == 0 {
             island_maps.get_map_mut(island.0).map(|map| map.enemy_count -= 1);
+
+            if let Some((loot, contributions)) = loot {
+                loot_dropped.write(LootDropped { island: island.0, table: loot.0.clone(), credited: contributions.credited() });
+            }
             
             commands.entity(entity).insert(RemoveEntity);
             
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::components::character::{LastMoveInput, LocalPlayer, MovePrediction};
 use crate::components::humanoid::ActionState;
 use crate::components::humanoid::PositionUpdate;
 use crate::components::humanoid::ServerPositionUpdate;
@@ -9,7 +10,10 @@ use crate::components::humanoid::Stunned;
 use crate::components::humanoid::ViewDirection;
 use crate::components::island::LeaveIsland;
 use crate::components::island::OnIsland;
+use crate::plugins::island_controls::apply_movement;
+use crate::plugins::lag_compensation::PositionHistory;
 use crate::plugins::network::OwnedBy;
+use crate::plugins::visibility::IslandOccupants;
 use crate::preludes::humanoid_preludes::*;
 use crate::preludes::network_preludes::*;
 use crate::components::island_maps::IslandMaps;
@@ -26,14 +30,21 @@ impl Plugin for HumanoidPlugin {
         .add_observer(position_trigger)
         .add_systems(PreUpdate,
         (
-            ((player_death_check, position_change_event).run_if(server_running),
-            (animate_movement,animate_view_direction).in_set(IslandSet)),
+            (player_death_check, position_change_event.after(apply_movement)).run_if(server_running),
             (sync_status_flags_system, status_flags_to_actionstate_system).chain(),
         ))
         .add_systems(Update, (remove_entities).run_if(server_running));
     }
 }
 
+pub struct HumanoidPresentationPlugin;
+impl Plugin for HumanoidPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(PreUpdate, (animate_movement,animate_view_direction).in_set(IslandSet));
+    }
+}
+
 fn player_death_check(
     mut commands: Commands,
     entities: Query<(&Health, Entity, &OwnedBy, &OnIsland), Without<Enemy>>,
@@ -63,11 +74,14 @@ fn remove_entities(
 fn position_change_event(
     mut commands: Commands,
     mut event: EventReader<PositionUpdate>,
-    mut entity_query: Query<(&mut Position, &OnIsland, Option<&Character>)>,
-    mut island_maps: ResMut<IslandMaps>
+    mut entity_query: Query<(&mut Position, &OnIsland, Option<&Character>, Option<&LastMoveInput>)>,
+    mut island_maps: ResMut<IslandMaps>,
+    mut history: ResMut<PositionHistory>,
+    server_tick: Res<ServerTick>,
+    occupants: Res<IslandOccupants>,
 ) {
     for PositionUpdate { new_position, entity } in event.read() {
-        if let Ok((mut entity_position, island, character)) = entity_query.get_mut(*entity) {
+        if let Ok((mut entity_position, island, character, last_input)) = entity_query.get_mut(*entity) {
             if let Some(map) = island_maps.get_map_mut(island.0) {
                 
                 let mut tile_type = TileType::Enemy;
@@ -75,17 +89,20 @@ fn position_change_event(
                     tile_type = TileType::Player;
                 }
 
+                history.record(island.0, server_tick.get(), *entity, entity_position.0);
                 map.remove_entity(entity_position.0);
                 map.add_entity_ivec3(*new_position, Tile::new(tile_type, *entity));
                 entity_position.0 = *new_position;
 
-                commands.server_trigger_targets(
-                    ToClients {
-                        mode: SendMode::BroadcastExcept(SERVER),
-                        event: ServerPositionUpdate { position: *new_position } ,
-                    },
-                    *entity,
-                );
+                for client in occupants.clients(island.0).iter().filter(|client| **client != SERVER) {
+                    commands.server_trigger_targets(
+                        ToClients {
+                            mode: SendMode::Direct(*client),
+                            event: ServerPositionUpdate { position: *new_position, last_input: last_input.map_or(0, |input| input.0) } ,
+                        },
+                        *entity,
+                    );
+                }
             }
         }
     }
@@ -93,10 +110,17 @@ fn position_change_event(
 
 fn position_trigger(
     trigger: Trigger<ServerPositionUpdate>,
-    mut entity_query: Query<(&mut Position, &mut ViewDirection, &OnIsland, Option<&Character>)>,
+    mut entity_query: Query<(&mut Position, &mut ViewDirection, &OnIsland, Option<&Character>, Option<&mut MovePrediction>, Has<LocalPlayer>)>,
     mut island_maps: ResMut<IslandMaps>
 ) {
-    if let Ok((mut position, mut view_direction, island, character)) = entity_query.get_mut(trigger.target()) {
+    let entity = trigger.target();
+    if let Ok((mut position, mut view_direction, island, character, prediction, local)) = entity_query.get_mut(entity) {
+        // The map snapshot may still be on its way, it places the entity once it arrives
+        if !island_maps.maps.contains_key(&island.0) {
+            position.0 = trigger.position;
+            return;
+        }
+
         if let Some(map) = island_maps.get_map_mut(island.0) {
             
             let mut tile_type = TileType::Enemy;
@@ -104,8 +128,25 @@ fn position_trigger(
                 tile_type = TileType::Player;
             }
 
+            // Roll back to the authoritative position and replay the inputs the server has not processed yet
+            if let (true, Some(mut prediction)) = (local, prediction) {
+                prediction.pending.retain(|(sequence, _)| *sequence > trigger.last_input);
+
+                map.remove_entity_if(position.0, entity);
+                let mut predicted = trigger.position;
+                for (_, direction) in prediction.pending.iter() {
+                    if let Some(next) = map.step_target(predicted, *direction) {
+                        predicted = next;
+                    }
+                }
+
+                map.add_entity_ivec3(predicted, Tile::new(tile_type, entity));
+                position.0 = predicted;
+                return;
+            }
+
             map.remove_entity(position.0);
-            map.add_entity_ivec3(trigger.position, Tile::new(tile_type, trigger.target()));
+            map.add_entity_ivec3(trigger.position, Tile::new(tile_type, entity));
             
             view_direction.0 = ((position.0 - trigger.position).as_vec3() * Vec3::new(1.0, 0.0, 1.0)).normalize_or_zero().round().as_ivec3();
 
@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+
+use crate::components::character::{Character, CombatStats};
+use crate::components::humanoid::Health;
+use crate::components::player::{ActiveBuffs, Buff, CharacterXp, Equipment, EquipmentSlot, Gold, Inventory, ItemCatalogue, ItemEffect, ItemStack, ItemStats, ItemUseRejected, ItemUseRejection, PlayerData, SkillLoadout, Stash, UnequipItem, UnlockedSkills, UseItem};
+use crate::plugins::attack::AttackRegistry;
+use crate::plugins::network::OwnedBy;
+use crate::plugins::progression::XpCurve;
+use crate::preludes::network_preludes::*;
+
+pub struct InventoryPlugin;
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .replicate::<PlayerData>()
+        .replicate::<Inventory>()
+        .replicate::<Stash>()
+        .replicate::<Equipment>()
+        .replicate::<CharacterXp>()
+        .replicate::<Gold>()
+        .replicate::<CombatStats>()
+        .add_client_event::<UseItem>(Channel::Ordered)
+        .add_client_event::<UnequipItem>(Channel::Ordered)
+        .add_server_event::<ItemUseRejected>(Channel::Ordered)
+        .add_systems(Update, (
+            (use_items, unequip_items, empty_stashes, tick_buffs, update_combat_stats).chain().run_if(server_running),
+            item_use_rejected,
+        ));
+    }
+}
+
+type PlayerInventories<'w, 's> = Query<'w, 's, (&'static OwnedBy, &'static mut Inventory, &'static mut Equipment, &'static mut UnlockedSkills, &'static mut SkillLoadout), With<PlayerData>>;
+type PlayerCharacters<'w, 's> = Query<'w, 's, (&'static OwnedBy, &'static mut Health, &'static mut ActiveBuffs), With<Character>>;
+
+fn use_items(
+    mut use_events: EventReader<FromClient<UseItem>>,
+    mut rejections: EventWriter<ToClients<ItemUseRejected>>,
+    catalogue: Res<ItemCatalogue>,
+    registry: Res<AttackRegistry>,
+    mut inventories: PlayerInventories,
+    mut characters: PlayerCharacters,
+) {
+    for FromClient { client_entity, event } in use_events.read() {
+        if let Err(reason) = use_item(*client_entity, event, &catalogue, &registry, &mut inventories, &mut characters) {
+            debug!("Rejected use of item {} from {:?}: {:?}", event.item, client_entity, reason);
+            rejections.write(ToClients {
+                mode: SendMode::Direct(*client_entity),
+                event: ItemUseRejected { item: event.item, reason },
+            });
+        }
+    }
+}
+
+fn use_item(
+    client_entity: Entity,
+    event: &UseItem,
+    catalogue: &ItemCatalogue,
+    registry: &AttackRegistry,
+    inventories: &mut PlayerInventories,
+    characters: &mut PlayerCharacters,
+) -> Result<(), ItemUseRejection> {
+    let Some((_, mut inventory, mut equipment, mut unlocked, mut loadout)) = inventories.iter_mut().find(|(owner, ..)| owner.0 == client_entity) else {
+        return Err(ItemUseRejection::NoSuchItem);
+    };
+
+    // The slot is checked against the item too, the stack may have moved since the click
+    let in_slot = inventory.slots.get(event.slot).and_then(|slot| slot.as_ref()).is_some_and(|stack| stack.id == event.item);
+    if !in_slot {
+        return Err(ItemUseRejection::NoSuchItem);
+    }
+
+    let effect = catalogue.0.get(&event.item).and_then(|spec| spec.effect.clone());
+
+    // Equipping and learning work anywhere, consumables only on an island
+    match effect {
+        Some(ItemEffect::Equipment { slot, .. }) => {
+            return equip(event.slot, slot, &mut inventory, &mut equipment, catalogue);
+        }
+        Some(ItemEffect::UnlockSkill(ref name)) => {
+            let attack = registry.id_of(name).ok_or(ItemUseRejection::NotUsable)?;
+            if unlocked.skills.contains(&attack) {
+                return Err(ItemUseRejection::AlreadyKnown);
+            }
+            inventory.take_from_slot(event.slot, 1).map_err(|_| ItemUseRejection::NoSuchItem)?;
+            unlocked.skills.push(attack);
+            loadout.add(attack);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let Some((_, mut health, mut buffs)) = characters.iter_mut().find(|(owner, _, _)| owner.0 == client_entity) else {
+        return Err(ItemUseRejection::NotOnIsland);
+    };
+    if health.get() == 0 {
+        return Err(ItemUseRejection::Dead);
+    }
+
+    match effect {
+        Some(ItemEffect::Heal(amount)) => {
+            if health.value >= health.max {
+                return Err(ItemUseRejection::FullHealth);
+            }
+            health.value = health.value.saturating_add(amount).min(health.max);
+        }
+        Some(ItemEffect::Buff { stats, seconds }) => {
+            buffs.0.push(Buff { stats, timer: Timer::from_seconds(seconds, TimerMode::Once) });
+        }
+        _ => return Err(ItemUseRejection::NotUsable),
+    }
+
+    inventory.take_from_slot(event.slot, 1).map_err(|_| ItemUseRejection::NoSuchItem)?;
+    Ok(())
+}
+
+// The item worn before goes back into the inventory
+fn equip(
+    from: usize,
+    slot: EquipmentSlot,
+    inventory: &mut Inventory,
+    equipment: &mut Equipment,
+    catalogue: &ItemCatalogue,
+) -> Result<(), ItemUseRejection> {
+    if let Some(worn) = equipment.get(slot) {
+        let frees_slot = inventory.slots[from].as_ref().is_some_and(|stack| stack.qty == 1);
+        if !frees_slot && !inventory.fits(&ItemStack { id: worn, qty: 1 }, catalogue) {
+            return Err(ItemUseRejection::InventoryFull);
+        }
+    }
+
+    let taken = inventory.take_from_slot(from, 1).map_err(|_| ItemUseRejection::NoSuchItem)?;
+    if let Some(worn) = equipment.slot_mut(slot).replace(taken.id) {
+        inventory.add(ItemStack { id: worn, qty: 1 }, catalogue);
+    }
+    Ok(())
+}
+
+fn unequip_items(
+    mut unequip_events: EventReader<FromClient<UnequipItem>>,
+    mut rejections: EventWriter<ToClients<ItemUseRejected>>,
+    catalogue: Res<ItemCatalogue>,
+    mut inventories: PlayerInventories,
+) {
+    for FromClient { client_entity, event } in unequip_events.read() {
+        let Some((_, mut inventory, mut equipment, ..)) = inventories.iter_mut().find(|(owner, ..)| owner.0 == *client_entity) else {
+            continue;
+        };
+        let Some(worn) = equipment.get(event.slot) else {
+            continue;
+        };
+
+        let stack = ItemStack { id: worn, qty: 1 };
+        if !inventory.fits(&stack, &catalogue) {
+            rejections.write(ToClients {
+                mode: SendMode::Direct(*client_entity),
+                event: ItemUseRejected { item: worn, reason: ItemUseRejection::InventoryFull },
+            });
+            continue;
+        }
+
+        inventory.add(stack, &catalogue);
+        *equipment.slot_mut(event.slot) = None;
+    }
+}
+
+/// Puts items in the inventory, whatever does not fit goes to the stash.
+pub fn give_items(inventory: &mut Inventory, stash: &mut Stash, items: impl IntoIterator<Item = ItemStack>, catalogue: &ItemCatalogue) {
+    for item in items {
+        if let Some(leftover) = inventory.add(item, catalogue) {
+            info!("Inventory full, {} of item {} went to the stash", leftover.qty, leftover.id);
+            stash.add(leftover);
+        }
+    }
+}
+
+// Whatever waits in the stash moves back as soon as the inventory has room for it
+fn empty_stashes(
+    catalogue: Res<ItemCatalogue>,
+    mut data: Query<(&mut Inventory, &mut Stash), (With<PlayerData>, Or<(Changed<Inventory>, Changed<Stash>)>)>,
+) {
+    for (mut inventory, mut stash) in data.iter_mut() {
+        if stash.items.is_empty() {
+            continue;
+        }
+
+        // Only flag a change when something moved, or a full inventory would be checked every frame
+        if stash.bypass_change_detection().move_into(inventory.bypass_change_detection(), &catalogue) {
+            stash.set_changed();
+            inventory.set_changed();
+        }
+    }
+}
+
+fn tick_buffs(
+    time: Res<Time>,
+    mut characters: Query<&mut ActiveBuffs>,
+) {
+    for mut buffs in characters.iter_mut() {
+        if buffs.0.is_empty() {
+            continue;
+        }
+
+        for buff in buffs.0.iter_mut() {
+            buff.timer.tick(time.delta());
+        }
+        buffs.0.retain(|buff| !buff.timer.finished());
+    }
+}
+
+// Levels, equipment and buffs on top of the base stats. Health keeps the same amount missing when its maximum changes.
+fn update_combat_stats(
+    catalogue: Res<ItemCatalogue>,
+    curve: Res<XpCurve>,
+    player_data: Query<(&OwnedBy, &Equipment, &CharacterXp), With<PlayerData>>,
+    mut characters: Query<(&OwnedBy, &ActiveBuffs, &mut CombatStats, &mut Health), With<Character>>,
+) {
+    for (owner, buffs, mut stats, mut health) in characters.iter_mut() {
+        let mut bonus = ItemStats::default();
+        if let Some((_, worn, xp)) = player_data.iter().find(|(data_owner, ..)| data_owner.0 == owner.0) {
+            bonus += curve.level_stats(xp.level);
+            for item in worn.items() {
+                if let Some(ItemEffect::Equipment { stats, .. }) = catalogue.0.get(&item).and_then(|spec| spec.effect.as_ref()) {
+                    bonus += *stats;
+                }
+            }
+        }
+        for buff in buffs.0.iter() {
+            bonus += buff.stats;
+        }
+
+        let new_stats = CombatStats::from_bonus(bonus);
+        if *stats == new_stats {
+            continue;
+        }
+
+        if health.max != new_stats.max_health {
+            let missing = health.max.saturating_sub(health.value);
+            health.max = new_stats.max_health;
+            if health.value > 0 {
+                health.value = health.max.saturating_sub(missing).max(1);
+            }
+        }
+        *stats = new_stats;
+    }
+}
+
+fn item_use_rejected(
+    mut rejections: EventReader<ItemUseRejected>,
+) {
+    for rejection in rejections.read() {
+        warn!("Could not use item {}: {:?}", rejection.item, rejection.reason);
+    }
+}
@@ -6,14 +6,21 @@ use crate::components::enemy::*;
 use crate::components::humanoid::*;
 use crate::components::island::*;
 use crate::components::island_maps::IslandMaps;
-use crate::components::island_maps::TerrainType;
+use crate::components::island_maps::{TerrainChanged, TerrainType};
 use crate::components::overworld::{LocalIsland, Island};
 use crate::islands::atoll::AtollPlugin;
 use crate::plugins::network::MakeLocal;
 use crate::components::character::LocalPlayer;
 use crate::plugins::camera::NewCameraTarget;
-use crate::components::character::Character;
+use crate::components::character::{Character, CombatStats, LastMoveInput};
+use crate::components::player::{CharacterXp, PlayerData};
+use crate::plugins::map_sync::MapSnapshotRequest;
 use crate::plugins::network::OwnedBy;
+use crate::plugins::visibility::IslandOccupants;
+use crate::plugins::loot::{Loot, LootDropped, LootRng};
+use crate::plugins::progression::XpCurve;
+use crate::plugins::shop::Merchant;
+use crate::plugins::world_state::WorldState;
 use crate::preludes::network_preludes::*;
 use crate::IslandSet;
 use crate::GameState;
@@ -27,15 +34,24 @@ impl Plugin for IslandPlugin {
         .add_server_event::<LeaveIsland>(Channel::Unordered)
         .replicate::<OnIsland>()
         .replicate::<Character>()
-        .add_systems(OnExit(GameState::Island), client_island_cleanup)
-        .add_systems(PreUpdate, ((clean_up_island, add_waiting_player).run_if(server_running), visualize_island))
+        .add_systems(PreUpdate, (clean_up_island, add_waiting_player).run_if(server_running))
         .add_systems(Update, (
-            (player_enters_island, player_leaves_island, elimination_island_objective).run_if(server_running),
-            (spawn_island_player, client_player_leaves_island).in_set(IslandSet)
+            (player_enters_island, player_leaves_island, elimination_island_objective, loot_chests).run_if(server_running),
+            client_player_leaves_island.in_set(IslandSet)
         ));
     }
 }
 
+pub struct IslandPresentationPlugin;
+impl Plugin for IslandPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnExit(GameState::Island), client_island_cleanup)
+        .add_systems(PreUpdate, visualize_island)
+        .add_systems(Update, (spawn_island_player, visualize_chest, update_terrain_blocks).in_set(IslandSet));
+    }
+}
+
 fn client_island_cleanup(
     mut commands: Commands,
     player_query: Query<Entity, With<Character>>,
@@ -112,52 +128,11 @@ fn visualize_island(
         let map = island_maps.maps.get_mut(&island.0).unwrap();
         for (pos, chunk) in map.chunks.iter() {
             for (idx, tile) in chunk.tiles.iter().enumerate() {
-                if !matches!(tile.kind, TileType::Terrain(_)) {
+                let TileType::Terrain(terrain) = tile.kind else {
                     continue;
-                }
+                };
                 let position = map.chunk_to_world_coords(*pos, idx);
-                let mut mesh : Handle<Mesh> = Handle::default();
-                let mut material : Handle<StandardMaterial> = Handle::default();
-                let mut scale : Vec3 = Vec3::ONE;
-
-                match tile.kind {
-                    TileType::Terrain(TerrainType::Sand) => {
-                        mesh = assets.load("blocks/SandBlockTest.glb#Mesh0/Primitive0").clone();
-                        material = assets.load("blocks/SandBlockTest.glb#Material0").clone();
-                    }
-                    TileType::Terrain(TerrainType::Rock) => {
-                        mesh = assets.load("blocks/RockBlock.glb#Mesh0/Primitive0").clone();
-                        material = assets.load("blocks/RockBlock.glb#Material0").clone();
-
-                        if let Some(mat_asset) = materials.get_mut(&material) {
-                            let mut mat = mat_asset.clone();
-                            let j = rand::random_range(0.90..=1.10);
-                            let color = mat.base_color.to_linear();
-                            mat.base_color = Color::srgba(color.red * j, color.green * j, color.blue * j, color.alpha);
-                            material = materials.add(mat);
-                        }
-                    }
-                    TileType::Terrain(TerrainType::Boardwalk) => {
-                        mesh = assets.load("blocks/BoardWalkBlock.glb#Mesh0/Primitive0").clone();
-                        material = assets.load("blocks/BoardWalkBlock.glb#Material0").clone();
-                    }
-                    TileType::Terrain(TerrainType::PalmTree) => {
-                        mesh = assets.load("blocks/PalmTreeTrunkBlock.glb#Mesh0/Primitive0").clone();
-                        material = assets.load("blocks/PalmTreeTrunkBlock.glb#Material0").clone();
-                    }
-                    TileType::Terrain(TerrainType::Leaves) => {
-                        mesh = meshes.add(Cuboid::new(1.0, 1.0, 1.0));
-                        material = materials.add(StandardMaterial {base_color: Color::srgb_u8(36, 80, 2), ..Default::default()});
-
-                    }
-                    _ => ()
-                }
-
-                commands.spawn((
-                    Mesh3d(mesh),
-                    MeshMaterial3d(material),
-                    Transform::from_xyz(position.x as f32, position.y as f32, position.z as f32).with_scale(scale),
-                )).insert(ChildOf(island_root));
+                spawn_terrain_block(&mut commands, &assets, &mut meshes, &mut materials, island_root, position, terrain);
             }
         }
 
@@ -176,14 +151,106 @@ fn visualize_island(
     }
 }  
 
+fn spawn_terrain_block(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    island_root: Entity,
+    position: IVec3,
+    terrain: TerrainType,
+) {
+    let mut mesh : Handle<Mesh> = Handle::default();
+    let mut material : Handle<StandardMaterial> = Handle::default();
+
+    match terrain {
+        TerrainType::Sand => {
+            mesh = assets.load("blocks/SandBlockTest.glb#Mesh0/Primitive0").clone();
+            material = assets.load("blocks/SandBlockTest.glb#Material0").clone();
+        }
+        TerrainType::Rock => {
+            mesh = assets.load("blocks/RockBlock.glb#Mesh0/Primitive0").clone();
+            material = assets.load("blocks/RockBlock.glb#Material0").clone();
+
+            if let Some(mat_asset) = materials.get_mut(&material) {
+                let mut mat = mat_asset.clone();
+                let j = rand::random_range(0.90..=1.10);
+                let color = mat.base_color.to_linear();
+                mat.base_color = Color::srgba(color.red * j, color.green * j, color.blue * j, color.alpha);
+                material = materials.add(mat);
+            }
+        }
+        TerrainType::Boardwalk => {
+            mesh = assets.load("blocks/BoardWalkBlock.glb#Mesh0/Primitive0").clone();
+            material = assets.load("blocks/BoardWalkBlock.glb#Material0").clone();
+        }
+        TerrainType::PalmTree => {
+            mesh = assets.load("blocks/PalmTreeTrunkBlock.glb#Mesh0/Primitive0").clone();
+            material = assets.load("blocks/PalmTreeTrunkBlock.glb#Material0").clone();
+        }
+        TerrainType::Leaves => {
+            mesh = meshes.add(Cuboid::new(1.0, 1.0, 1.0));
+            material = materials.add(StandardMaterial {base_color: Color::srgb_u8(36, 80, 2), ..Default::default()});
+        }
+        _ => ()
+    }
+
+    commands.spawn((
+        TerrainBlock(position),
+        Mesh3d(mesh),
+        MeshMaterial3d(material),
+        Transform::from_xyz(position.x as f32, position.y as f32, position.z as f32),
+    )).insert(ChildOf(island_root));
+}
+
+fn update_terrain_blocks(
+    mut commands: Commands,
+    mut changed: EventReader<TerrainChanged>,
+    island_maps: Res<IslandMaps>,
+    local_island: Query<&Island, With<LocalIsland>>,
+    islandroot_query: Query<Entity, With<IslandRoot>>,
+    blocks: Query<(Entity, &TerrainBlock)>,
+    mut meshes: ResMut<Assets<Mesh>>, 
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<AssetServer>,
+) {
+    let (Ok(local_island), Ok(island_root)) = (local_island.single(), islandroot_query.single()) else {
+        return;
+    };
+
+    for change in changed.read() {
+        if change.island != local_island.0 {
+            continue;
+        }
+
+        for (entity, block) in blocks.iter() {
+            if block.0 == change.position {
+                commands.entity(entity).despawn();
+            }
+        }
+
+        let Some(map) = island_maps.get_map(change.island) else {
+            continue;
+        };
+
+        if let TileType::Terrain(terrain) = map.get_tile(change.position).kind {
+            spawn_terrain_block(&mut commands, &assets, &mut meshes, &mut materials, island_root, change.position, terrain);
+        }
+    }
+}
+
 fn add_waiting_player(
     mut commands: Commands,
-    players: Query<(Entity, &OnIsland), With<Waiting>>,
+    mut snapshot_requests: EventWriter<MapSnapshotRequest>,
+    players: Query<(Entity, &OnIsland, &OwnedBy), With<Waiting>>,
     mut islands: ResMut<IslandMaps>,
-    position_query: Query<&Position>
+    position_query: Query<(&Position, Option<&LastMoveInput>)>,
+    occupants: Res<IslandOccupants>,
 ) {
-    for (player_entity, island) in players.iter() {
+    for (player_entity, island, owner) in players.iter() {
         if let Some(map) = islands.maps.get_mut(&island.0) {
+            snapshot_requests.write(MapSnapshotRequest { island: island.0, client_entity: owner.0 });
+
             let mut spawn_pos = map.leave_position;
             spawn_pos.y += 2;
             while map.get_tile(spawn_pos).kind != TileType::Empty {
@@ -192,20 +259,23 @@ fn add_waiting_player(
 
             commands.entity(player_entity).insert(Position::new(spawn_pos)).remove::<Waiting>();
 
-            commands.server_trigger_targets(
-                ToClients {
-                    mode: SendMode::BroadcastExcept(SERVER),
-                    event: ServerPositionUpdate { position: spawn_pos } ,
-                },
-                player_entity,
-            );
+            for client in occupants.clients(island.0).iter().filter(|client| **client != SERVER) {
+                commands.server_trigger_targets(
+                    ToClients {
+                        mode: SendMode::Direct(*client),
+                        event: ServerPositionUpdate { position: spawn_pos, last_input: 0 } ,
+                    },
+                    player_entity,
+                );
+            }
 
-            for entity in map.entities.iter() {
-                if let Ok(position) = position_query.get(*entity) {
+            // Only the newcomer is missing the positions of everything already on the island
+            for entity in map.entities.iter().filter(|_| owner.0 != SERVER) {
+                if let Ok((position, last_input)) = position_query.get(*entity) {
                     commands.server_trigger_targets(
                         ToClients {
-                            mode: SendMode::BroadcastExcept(SERVER),
-                            event: ServerPositionUpdate { position: position.0 } ,
+                            mode: SendMode::Direct(owner.0),
+                            event: ServerPositionUpdate { position: position.0, last_input: last_input.map_or(0, |input| input.0) } ,
                         },
                         *entity,
                     );
@@ -220,13 +290,47 @@ fn add_waiting_player(
 fn player_enters_island(
     mut commands: Commands,
     mut island_enter_event: EventReader<FromClient<EnteredIsland>>,
+    mut snapshot_requests: EventWriter<MapSnapshotRequest>,
     islands: Query<(Entity, &Island)>,
+    characters: Query<(Entity, &OwnedBy, &OnIsland), With<Character>>,
+    island_maps: Res<IslandMaps>,
+    position_query: Query<&Position>,
+    player_data: Query<(&OwnedBy, &CharacterXp), With<PlayerData>>,
+    curve: Res<XpCurve>,
 ) {
     for FromClient { client_entity, event } in island_enter_event.read() {
         let island_id = event.0;
+
+        // A resumed session already has its character here, only the positions need to be resent
+        if let Some((character, ..)) = characters.iter().find(|(_, owner, island)| owner.0 == *client_entity && island.0 == island_id) {
+            // The new connection counts its movement inputs from zero again
+            commands.entity(character).insert(LastMoveInput::default());
+            snapshot_requests.write(MapSnapshotRequest { island: island_id, client_entity: *client_entity });
+
+            if let Some(map) = island_maps.get_map(island_id) {
+                for entity in map.entities.iter() {
+                    if let Ok(position) = position_query.get(*entity) {
+                        commands.server_trigger_targets(
+                            ToClients {
+                                mode: SendMode::Direct(*client_entity),
+                                event: ServerPositionUpdate { position: position.0, last_input: 0 } ,
+                            },
+                            *entity,
+                        );
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Equipment and buffs are added on top once the character exists
+        let level = player_data.iter().find(|(owner, _)| owner.0 == *client_entity).map_or(1, |(_, xp)| xp.level);
+        let stats = CombatStats::from_bonus(curve.level_stats(level));
+
         let player_entity = commands.spawn((
             Character,
-            Health::new(200),
+            Health::new(stats.max_health),
+            stats,
             OwnedBy(*client_entity),
             Waiting,
             OnIsland(island_id),
@@ -253,47 +357,90 @@ fn elimination_island_objective(
     mut commands: Commands,
     target_query: Query<(Entity, &Island), (With<FinishedSetupIsland>, With<EliminationObjective>, Without<CompletedIslandObjective>)>,
     mut island_maps: ResMut<IslandMaps>,
-    mut meshes: ResMut<Assets<Mesh>>, 
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    islandroot_query: Query<Entity, With<IslandRoot>>
+    mut world: ResMut<WorldState>,
+    mut rng: ResMut<LootRng>,
 ) {
     for (entity, island) in target_query.iter() {
         if let Some(map) = island_maps.get_map_mut(island.0) {
             if map.enemy_count == 0 {
-                if let Ok(island_root) = islandroot_query.single() {
-                    let top_tiles = map.above_water_top_tiles();
-                    let chest_pos = top_tiles.choose(&mut rand::rng()).unwrap().clone() + IVec3::Y;
-
-                    println!("{} spawning chest", chest_pos);
-                    let chest_entity = commands.spawn((
-                        Mesh3d(meshes.add(Cuboid::new(1.05, 1.05, 1.05))),
-                        MeshMaterial3d(materials.add(StandardMaterial {
-                            base_color: Color::srgb(1.0, 1.0, 1.0),
-                            ..Default::default()
-                        })),
-                        Transform::from_xyz(chest_pos.x as f32, chest_pos.y as f32, chest_pos.z as f32),
-                        Position::new(chest_pos),
-                        Chest,
-                        Health::new(30),
-                        OnIsland(island.0),
-                    )).insert(ChildOf(island_root)).id();
-
-                    map.add_entity_ivec3(chest_pos, Tile::new(TileType::Enemy, chest_entity));
-                    commands.entity(entity).insert(CompletedIslandObjective);
-                }
+                let top_tiles = map.above_water_top_tiles();
+                let chest_pos = top_tiles.choose(&mut rng.0).unwrap().clone() + IVec3::Y;
+                let tier = ChestTier::WEIGHTS.choose_weighted(&mut rng.0, |(_, weight)| *weight).map_or(ChestTier::Common, |(tier, _)| *tier);
+
+                debug!("Spawning {:?} chest at {} on island {}", tier, chest_pos, island.0);
+                spawn_chest(&mut commands, map, island.0, chest_pos, tier);
+                commands.entity(entity).insert(CompletedIslandObjective);
+
+                let state = world.island_mut(island.0);
+                state.completed = true;
+                state.chest = Some(chest_pos);
+                state.chest_tier = tier;
             }
         }
     }
 }
 
+pub fn spawn_chest(commands: &mut Commands, map: &mut Map, island: u64, position: IVec3, tier: ChestTier) -> Entity {
+    let chest_entity = commands.spawn((
+        Position::new(position),
+        Chest,
+        Loot::new(tier.loot_table()),
+        Health::new(30),
+        OnIsland(island),
+    )).id();
+
+    map.add_entity_ivec3(position, Tile::new(TileType::Enemy, chest_entity));
+    chest_entity
+}
+
+fn loot_chests(
+    mut commands: Commands,
+    chests: Query<(Entity, &Health, &OnIsland, &Loot), (With<Chest>, Without<RemoveEntity>)>,
+    mut world: ResMut<WorldState>,
+    mut loot_dropped: EventWriter<LootDropped>,
+    occupants: Res<IslandOccupants>,
+) {
+    for (entity, health, island, loot) in chests.iter() {
+        if health.get() == 0 {
+            commands.entity(entity).insert(RemoveEntity);
+            world.island_mut(island.0).chest = None;
+            // Opening a chest is shared, everyone on the island gets a roll
+            loot_dropped.write(LootDropped { island: island.0, table: loot.0.clone(), credited: occupants.clients(island.0).to_vec() });
+        }
+    }
+}
+
+fn visualize_chest(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>, 
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    chests: Query<(Entity, &Position), (With<Chest>, Without<Mesh3d>)>,
+    islandroot_query: Query<Entity, With<IslandRoot>>
+) {
+    let Ok(island_root) = islandroot_query.single() else {
+        return;
+    };
+
+    for (entity, position) in chests.iter() {
+        commands.entity(entity).insert((
+            Mesh3d(meshes.add(Cuboid::new(1.05, 1.05, 1.05))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(1.0, 1.0, 1.0),
+                ..Default::default()
+            })),
+            Transform::from_xyz(position.0.x as f32, position.0.y as f32, position.0.z as f32),
+        )).insert(ChildOf(island_root));
+    }
+}
+
 fn client_player_leaves_island(
     mut state: ResMut<NextState<GameState>>,
     mut leave_island_event: EventReader<LeaveIsland>,
     mut islands: ResMut<IslandMaps>,
-    client: Option<Res<RenetClient>>
+    client: Res<RepliconClient>
 ) {   
     for event in leave_island_event.read() {
-        if client.is_some() {
+        if client.is_connected() {
             islands.maps.remove(&event.0);
             println!("Deleting island on client");
         }
@@ -313,9 +460,7 @@ fn player_leaves_island(
                 println!("{:?} leaves island", entity);
 
                 commands.entity(entity).despawn();
-                map.remove_entity(position.0);
-                map.entities.remove(&entity);
-                map.player_count -= 1;
+                map.remove_player(position.0, entity);
                 leave_island_event.write(ToClients { mode: SendMode::Direct(owner.0), event: LeaveIsland(island.0) });    
             }
         }
@@ -325,9 +470,9 @@ fn player_leaves_island(
 fn clean_up_island(
     mut commands: Commands,
     mut island_maps: ResMut<IslandMaps>,
-    enemy_query: Query<(Entity, &OnIsland), With<Enemy>>,
+    enemy_query: Query<(Entity, &OnIsland), Or<(With<Enemy>, With<Chest>, With<Merchant>)>>, // chests come back from the world state, merchants from their shop
     mut islands: Query<(Entity, &Island), With<MapFinishedIsland>>,
-    players: Query<&OnIsland, Without<Enemy>>
+    players: Query<&OnIsland, With<Character>>
 ) {
     let mut player_count: HashSet<u64> = HashSet::new();
     for island in players.iter() {
@@ -1,8 +1,7 @@
 use bevy::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
 use crate::attacks::base_attack::BaseAttack;
-use crate::attacks::counter::Counter;
-use crate::attacks::cut_through::CutThrough;
-use crate::attacks::dagger_throw::DaggerThrow;
 use crate::components::character::PendingSkillCast;
 use crate::components::humanoid::ActionState;
 use crate::components::humanoid::PositionUpdate;
@@ -11,6 +10,9 @@ use crate::components::island::OnIsland;
 use crate::components::island_maps::IslandMaps;
 use crate::components::character::LocalPlayer;
 use crate::components::character::MovementCooldown;
+use crate::components::character::{CombatStats, LastMoveInput, MovePrediction, StepBudget, MAX_STEP_BURST, STEP_SECONDS, TAP_STEP_SECONDS};
+use crate::components::humanoid::ServerPositionUpdate;
+use crate::components::player::{PlayerData, SkillLoadout, SKILL_SLOTS};
 use crate::plugins::attack::key_of;
 use crate::plugins::attack::AttackEvent;
 use crate::preludes::humanoid_preludes::*;
@@ -25,9 +27,16 @@ impl Plugin for CharacterPlugin {
         app
         .add_client_event::<MoveDirection>(Channel::Ordered)
         .insert_resource(MovementCooldown {
-            timer: Timer::from_seconds(0.2, TimerMode::Once),
+            timer: Timer::from_seconds(STEP_SECONDS, TimerMode::Once),
         })
-        .add_systems(PreUpdate, (apply_movement).run_if(server_running))
+        .add_systems(PreUpdate, (apply_movement).run_if(server_running));
+    }
+}
+
+pub struct CharacterPresentationPlugin;
+impl Plugin for CharacterPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
         .add_systems(Update,
             (movement_input, attack_input, skill_input, resolve_pending_skill_cast.after(skill_input)).in_set(IslandSet)
         );
@@ -38,16 +47,21 @@ fn movement_input(
     mut move_events: EventWriter<MoveDirection>, 
     input: Res<ButtonInput<KeyCode>>,
     camera: Query<&DollyCamera, With<PlayerCamera>>,
-    player: Query<(Entity, &ActionState), (With<LocalPlayer>, With<Character>)>,
+    mut player: Query<(Entity, &ActionState, &mut Position, &mut MovePrediction, &OnIsland, &CombatStats), (With<LocalPlayer>, With<Character>)>,
     time: Res<Time>,
     mut cooldown: ResMut<MovementCooldown>,
-    mut view_direction_q: Query<&mut ViewDirection>
+    mut view_direction_q: Query<&mut ViewDirection>,
+    mut islands: ResMut<IslandMaps>,
+    client: Res<RepliconClient>,
 ) {
     cooldown.timer.tick(time.delta());
 
-    let Ok((entity, action_state)) = player.single() else {
+    let Ok((entity, action_state, mut position, mut prediction, island, stats)) = player.single_mut() else {
         return;
     };
+
+    // Faster characters repeat steps sooner, the server holds them to the same pace
+    cooldown.timer.set_duration(Duration::from_secs_f32(STEP_SECONDS / stats.move_speed));
     
     if *action_state != ActionState::Idle && *action_state != ActionState::Moving {
         return;
@@ -98,10 +112,25 @@ fn movement_input(
         view_direction.0 = -direction;
     }
 
-    if (just_pressed && cooldown.timer.elapsed_secs() >= 0.05)
+    if (just_pressed && cooldown.timer.elapsed_secs() >= TAP_STEP_SECONDS / stats.move_speed)
         || (!just_pressed && cooldown.timer.finished())
     {
-        move_events.write(MoveDirection(direction));
+        prediction.next_sequence += 1;
+        let sequence = prediction.next_sequence;
+
+        // The host moves through the server directly, a remote client predicts the step right away
+        if client.is_connected() {
+            if let Some(map) = islands.get_map_mut(island.0) {
+                if let Some(new_position) = map.step_target(position.0, direction) {
+                    map.remove_entity_if(position.0, entity);
+                    map.add_entity_ivec3(new_position, Tile::new(TileType::Player, entity));
+                    position.0 = new_position;
+                }
+            }
+            prediction.pending.push_back((sequence, direction));
+        }
+
+        move_events.write(MoveDirection { direction, sequence });
         cooldown.timer.reset();
     }
 }
@@ -160,80 +189,70 @@ fn attack_input(
 }
 
 pub fn apply_movement(
+    mut commands: Commands,
     mut move_events: EventReader<FromClient<MoveDirection>>,
     mut position_event: EventWriter<PositionUpdate>,
-    players: Query<(&OwnedBy, &Position, Entity, &OnIsland), With<Character>>,
-    mut islands: ResMut<IslandMaps>,
+    mut players: Query<(&OwnedBy, &Position, &mut LastMoveInput, &mut StepBudget, &CombatStats, Entity, &OnIsland), With<Character>>,
+    islands: Res<IslandMaps>,
+    time: Res<Time>,
 ) {
+    for (.., mut budget, stats, _, _) in players.iter_mut() {
+        budget.0 = (budget.0 + time.delta_secs() * stats.move_speed / TAP_STEP_SECONDS).min(MAX_STEP_BURST);
+    }
+
+    // Several inputs of one character can arrive in the same frame, each builds on the step before it
+    let mut pending_positions: HashMap<Entity, IVec3> = HashMap::new();
+
     for FromClient { client_entity, event } in move_events.read() {
-        for (owner, position, player_entity, island) in players.iter() {
-            if let Some(map) = islands.get_map_mut(island.0) {
-                if *client_entity == owner.0 {
-                    let mut new_position = event.0;
-                    let current_pos = position.0.clone();
-                    new_position += current_pos;
-                    
-                    match map.get_tile(new_position).kind {
-                        TileType::Terrain(_) => {
-                            new_position.y += 1;
-                            let tile_above = map.get_tile(new_position);
-
-                            if tile_above.kind != TileType::Empty {
-                                return;
-                            }
-                        }
-                        TileType::Empty => {
-                            if new_position.y != 0 {
-                                let mut temp_pos = new_position;
-                                temp_pos.y -= 1;
-                                let tile_below = map.get_tile(temp_pos);
-
-                                if tile_below.kind == TileType::Empty {
-                                    temp_pos.y -= 1;
-                                    let tile_below_terrain = map.get_tile(temp_pos);
-                                    if !matches!(tile_below_terrain.kind, TileType::Terrain(_)) {
-                                        return;
-                                    }
-                                    new_position.y -= 1;
-                                }
-                            }
-                        }
-                        _ => {
-                            return;
-                        }
-                    }
+        for (owner, position, mut last_input, mut budget, _, player_entity, island) in players.iter_mut() {
+            if *client_entity != owner.0 {
+                continue;
+            }
+
+            last_input.0 = event.sequence;
+
+            let Some(map) = islands.get_map(island.0) else {
+                continue;
+            };
+
+            let current = *pending_positions.get(&player_entity).unwrap_or(&position.0);
+            let horizontal_step = event.direction.y == 0 && event.direction.abs().element_sum() == 1;
+            if horizontal_step && budget.0 >= 1.0 {
+                if let Some(new_position) = map.step_target(current, event.direction) {
+                    budget.0 -= 1.0;
+                    pending_positions.insert(player_entity, new_position);
                     position_event.write(PositionUpdate { new_position: new_position, entity: player_entity });
+                    continue;
                 }
             }
+
+            // Rejected or too fast step, the owner predicted it already and has to roll back
+            commands.server_trigger_targets(
+                ToClients {
+                    mode: SendMode::Direct(owner.0),
+                    event: ServerPositionUpdate { position: current, last_input: event.sequence },
+                },
+                player_entity,
+            );
         }
     }
 }
 
+/// Keys for the skill slots of the `SkillLoadout`, in order
+const SKILL_KEYS: [KeyCode; SKILL_SLOTS] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4];
+
 fn skill_input(
     mut commands: Commands,
     input: Res<ButtonInput<KeyCode>>,
     player: Query<Entity, (With<LocalPlayer>, With<Character>, Without<PendingSkillCast>)>,
+    loadout: Query<&SkillLoadout, (With<PlayerData>, With<LocalPlayer>)>,
 ) {
-    let Ok(entity) = player.single() else {
+    let (Ok(entity), Ok(loadout)) = (player.single(), loadout.single()) else {
         return;
     };
 
-    let mut attack_id = None;
-
-    if input.just_pressed(KeyCode::Digit1) {
-        attack_id = Some(key_of::<Counter>());
-    }
-    if input.just_pressed(KeyCode::Digit2) {
-        attack_id = Some(key_of::<CutThrough>());
-    }
-    if input.just_pressed(KeyCode::Digit3) {
-        attack_id = Some(key_of::<DaggerThrow>());
-    }
-    if input.just_pressed(KeyCode::Digit4) {
-        attack_id = Some(key_of::<BaseAttack>());
-    }
-
-    if let Some(attack_id) = attack_id {
+    let pressed = SKILL_KEYS.iter().position(|key| input.just_pressed(*key));
+    if let Some(attack_id) = pressed.and_then(|slot| loadout.0[slot]) {
         commands.entity(entity).insert(PendingSkillCast { attack_id });
     }
 }
@@ -0,0 +1,91 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::components::player::{ItemCatalogue, ItemSpec};
+
+const ITEM_FOLDER: &str = "items";
+
+/// One data file from `assets/items`, either `*.items.ron` or `*.items.json`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct ItemList(pub Vec<ItemSpec>);
+
+#[derive(Default)]
+struct ItemListLoader;
+
+impl AssetLoader for ItemListLoader {
+    type Asset = ItemList;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<ItemList, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let is_json = load_context.path().extension().is_some_and(|extension| extension == "json");
+        if is_json {
+            Ok(serde_json::from_slice(&bytes)?)
+        } else {
+            Ok(ron::de::from_bytes(&bytes)?)
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["items.ron", "items.json"]
+    }
+}
+
+/// Keeps the item folder loaded, with the `dev` feature its files are watched for changes.
+#[derive(Resource)]
+struct ItemFiles {
+    _folder: Handle<LoadedFolder>,
+}
+
+pub struct ItemPlugin;
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<ItemCatalogue>()
+        .init_asset::<ItemList>()
+        .init_asset_loader::<ItemListLoader>()
+        .add_systems(Startup, load_item_files)
+        .add_systems(PreUpdate, update_item_catalogue);
+    }
+}
+
+fn load_item_files(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(ItemFiles { _folder: assets.load_folder(ITEM_FOLDER) });
+}
+
+// Rebuilt from every loaded file whenever one of them is added, changed or removed
+fn update_item_catalogue(
+    mut events: EventReader<AssetEvent<ItemList>>,
+    lists: Res<Assets<ItemList>>,
+    mut catalogue: ResMut<ItemCatalogue>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    catalogue.0.clear();
+    for (_, list) in lists.iter() {
+        for spec in list.0.iter() {
+            if let Some(existing) = catalogue.0.insert(spec.id, spec.clone()) {
+                warn!("Item id {} is used by both '{}' and '{}'", spec.id, existing.name, spec.name);
+            }
+        }
+    }
+
+    info!("Item catalogue holds {} items", catalogue.0.len());
+}
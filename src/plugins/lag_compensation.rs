@@ -0,0 +1,77 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+use crate::components::island_maps::IslandMaps;
+use crate::preludes::network_preludes::*;
+
+/// Furthest a client hit is rewound, about a third of a second at replicon's default tick rate.
+pub const MAX_REWIND_TICKS: u32 = 10;
+
+struct TileMove {
+    tick: u32,
+    entity: Entity,
+    from: IVec3,
+}
+
+/// Recent tile moves per island on the server, oldest first.
+#[derive(Resource, Default)]
+pub struct PositionHistory {
+    islands: HashMap<u64, VecDeque<TileMove>>,
+}
+
+impl PositionHistory {
+    pub fn record(&mut self, island: u64, tick: u32, entity: Entity, from: IVec3) {
+        let moves = self.islands.entry(island).or_default();
+        while moves.front().is_some_and(|oldest| tick.saturating_sub(oldest.tick) > MAX_REWIND_TICKS) {
+            moves.pop_front();
+        }
+        moves.push_back(TileMove { tick, entity, from });
+    }
+
+    /// Where the entities that moved since `tick` stood back then.
+    fn rewind(&self, island: u64, tick: u32) -> HashMap<Entity, IVec3> {
+        let mut positions = HashMap::new();
+        if let Some(moves) = self.islands.get(&island) {
+            // Walking back in time, the oldest move since `tick` writes last
+            for tile_move in moves.iter().rev().take_while(|tile_move| tile_move.tick > tick) {
+                positions.insert(tile_move.entity, tile_move.from);
+            }
+        }
+        positions
+    }
+}
+
+/// How many server ticks behind a client was when it cast its latest attack.
+#[derive(Component, Default)]
+pub struct CasterLag(pub u32);
+
+/// Resolves what an attack hits. Attacks cast by clients are judged against the positions the
+/// caster saw when it attacked, everything else against the current map.
+#[derive(SystemParam)]
+pub struct HitTargets<'w, 's> {
+    island_maps: Res<'w, IslandMaps>,
+    history: Res<'w, PositionHistory>,
+    server_tick: Res<'w, ServerTick>,
+    lag: Query<'w, 's, &'static CasterLag>,
+}
+
+impl HitTargets<'_, '_> {
+    pub fn target(&self, attacker: Entity, island: u64, tile: IVec3) -> Option<Entity> {
+        let map = self.island_maps.get_map(island)?;
+        let lag = self.lag.get(attacker).map_or(0, |lag| lag.0.min(MAX_REWIND_TICKS));
+        if lag == 0 {
+            return map.get_target(tile);
+        }
+
+        let mut rewound = self.history.rewind(island, self.server_tick.get().saturating_sub(lag));
+        rewound.remove(&attacker);
+
+        if let Some((entity, _)) = rewound.iter().find(|(entity, position)| **position == tile && map.entities.contains(*entity)) {
+            return Some(*entity);
+        }
+
+        // Whoever stands there now only counts if it was already there back then
+        map.get_target(tile).filter(|entity| !rewound.contains_key(entity))
+    }
+}
@@ -0,0 +1,418 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::components::overworld::WorldSeed;
+use crate::components::player::{CharacterXp, Gold, Inventory, ItemCatalogue, ItemId, ItemStack, PlayerData, Stash};
+use crate::plugins::inventory::give_items;
+use crate::plugins::network::OwnedBy;
+use crate::plugins::visibility::IslandOccupants;
+use crate::preludes::network_preludes::*;
+
+const LOOT_FOLDER: &str = "loot";
+/// Keeps loot rolls apart from the terrain generation that uses the same seed.
+const LOOT_SEED_SALT: u64 = 0x6c6f_6f74;
+
+/// A weighted loot table from `assets/loot`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootTable {
+    pub name: String,
+    /// Inclusive range
+    #[serde(default)]
+    pub xp: (u64, u64),
+    /// Inclusive range
+    #[serde(default)]
+    pub gold: (u64, u64),
+    /// How many entries are drawn from the table
+    #[serde(default = "one")]
+    pub rolls: u32,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// One outcome of a roll. An entry without an item drops nothing, its weight sets how likely that is.
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootEntry {
+    #[serde(default)]
+    pub item: Option<ItemId>,
+    pub weight: u32,
+    /// Inclusive range
+    #[serde(default = "single")]
+    pub qty: (u16, u16),
+}
+
+fn single() -> (u16, u16) {
+    (1, 1)
+}
+
+#[derive(Default, Debug)]
+pub struct LootRoll {
+    pub xp: u64,
+    pub gold: u64,
+    pub items: Vec<ItemStack>,
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut impl Rng) -> LootRoll {
+        let mut roll = LootRoll {
+            xp: roll_range(rng, self.xp),
+            gold: roll_range(rng, self.gold),
+            items: Vec::new(),
+        };
+
+        let total: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        if total == 0 {
+            return roll;
+        }
+
+        for _ in 0..self.rolls {
+            let mut pick = rng.random_range(0..total);
+            let Some(entry) = self.entries.iter().find(|entry| {
+                if pick < entry.weight {
+                    return true;
+                }
+                pick -= entry.weight;
+                false
+            }) else {
+                continue;
+            };
+
+            let Some(id) = entry.item else { continue };
+            let qty = roll_range(rng, entry.qty);
+            match roll.items.iter_mut().find(|stack| stack.id == id) {
+                Some(stack) => stack.qty = stack.qty.saturating_add(qty),
+                None if qty > 0 => roll.items.push(ItemStack { id, qty }),
+                None => {}
+            }
+        }
+
+        roll
+    }
+}
+
+fn roll_range<T: rand::distr::uniform::SampleUniform + PartialOrd + Copy>(rng: &mut impl Rng, (min, max): (T, T)) -> T {
+    if min < max { rng.random_range(min..=max) } else { min }
+}
+
+/// One data file from `assets/loot`, `*.loot.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct LootTableList(pub Vec<LootTable>);
+
+#[derive(Default)]
+struct LootTableListLoader;
+
+impl AssetLoader for LootTableListLoader {
+    type Asset = LootTableList;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<LootTableList, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["loot.ron"]
+    }
+}
+
+/// Keeps the loot folder loaded, with the `dev` feature its files are watched for changes.
+#[derive(Resource)]
+struct LootFiles {
+    _folder: Handle<LoadedFolder>,
+}
+
+/// Every loot table by name.
+#[derive(Resource, Default)]
+pub struct LootTables(pub HashMap<String, LootTable>);
+
+/// Server side randomness for loot, seeded from the world seed.
+#[derive(Resource)]
+pub struct LootRng(pub ChaCha8Rng);
+
+impl Default for LootRng {
+    fn default() -> Self {
+        LootRng(ChaCha8Rng::seed_from_u64(LOOT_SEED_SALT))
+    }
+}
+
+/// Share of an enemy's damage a player has to deal to get a roll of its loot.
+pub const MIN_CREDIT_SHARE: f32 = 0.1;
+
+/// Name of the loot table an enemy or chest drops from.
+#[derive(Component, Clone, Debug)]
+#[require(DamageContributions)]
+pub struct Loot(pub String);
+
+impl Loot {
+    pub fn new(table: &str) -> Self {
+        Loot(table.to_string())
+    }
+}
+
+/// Damage each player dealt to something that drops loot, kept on the server.
+#[derive(Component, Default, Debug)]
+pub struct DamageContributions {
+    damage: HashMap<Entity, u64>,
+    last_hit: Option<Entity>,
+}
+
+impl DamageContributions {
+    pub fn record(&mut self, client: Entity, amount: u64) {
+        *self.damage.entry(client).or_default() += amount;
+        self.last_hit = Some(client);
+    }
+
+    /// Players who dealt a fair share of the damage, the last hit always counts.
+    pub fn credited(&self) -> Vec<Entity> {
+        let total: u64 = self.damage.values().sum();
+        let mut credited: Vec<Entity> = self.damage.iter()
+            .filter(|(_, damage)| **damage as f32 >= total as f32 * MIN_CREDIT_SHARE)
+            .map(|(client, _)| *client)
+            .collect();
+
+        if let Some(last_hit) = self.last_hit.filter(|client| !credited.contains(client)) {
+            credited.push(last_hit);
+        }
+        credited
+    }
+}
+
+/// Something on an island dropped its loot, every credited player still there gets a roll.
+#[derive(Event)]
+pub struct LootDropped {
+    pub island: u64,
+    pub table: String,
+    pub credited: Vec<Entity>,
+}
+
+/// What the server gave a player, it is already added to the replicated `PlayerData`.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct LootGranted {
+    pub xp: u64,
+    pub gold: u64,
+    pub items: Vec<ItemStack>,
+}
+
+pub struct LootPlugin;
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<LootTables>()
+        .init_resource::<LootRng>()
+        .init_asset::<LootTableList>()
+        .init_asset_loader::<LootTableListLoader>()
+        .add_event::<LootDropped>()
+        .add_server_event::<LootGranted>(Channel::Ordered)
+        .add_systems(Startup, load_loot_files)
+        .add_systems(PreUpdate, update_loot_tables)
+        .add_systems(Update, (
+            seed_loot_rng.run_if(resource_changed::<WorldSeed>),
+            grant_loot.run_if(server_running),
+            loot_granted,
+        ).chain());
+    }
+}
+
+fn load_loot_files(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(LootFiles { _folder: assets.load_folder(LOOT_FOLDER) });
+}
+
+fn update_loot_tables(
+    mut events: EventReader<AssetEvent<LootTableList>>,
+    lists: Res<Assets<LootTableList>>,
+    mut tables: ResMut<LootTables>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    tables.0.clear();
+    for (_, list) in lists.iter() {
+        for table in list.0.iter() {
+            if tables.0.insert(table.name.clone(), table.clone()).is_some() {
+                warn!("Loot table '{}' is defined more than once", table.name);
+            }
+        }
+    }
+
+    info!("Loaded {} loot tables", tables.0.len());
+}
+
+fn seed_loot_rng(
+    mut rng: ResMut<LootRng>,
+    seed: Res<WorldSeed>,
+) {
+    rng.0 = ChaCha8Rng::seed_from_u64(seed.0 ^ LOOT_SEED_SALT);
+}
+
+fn grant_loot(
+    mut dropped: EventReader<LootDropped>,
+    mut granted: EventWriter<ToClients<LootGranted>>,
+    tables: Res<LootTables>,
+    catalogue: Res<ItemCatalogue>,
+    occupants: Res<IslandOccupants>,
+    mut rng: ResMut<LootRng>,
+    mut player_data: Query<(&OwnedBy, &mut CharacterXp, &mut Gold, &mut Inventory, &mut Stash), With<PlayerData>>,
+) {
+    for LootDropped { island, table, credited } in dropped.read() {
+        let Some(table) = tables.0.get(table) else {
+            warn!("Unknown loot table '{table}'");
+            continue;
+        };
+
+        // Every credited player gets their own roll, as long as they did not leave the island
+        for client in credited.iter().filter(|client| occupants.contains(*island, **client)) {
+            let Some((_, mut xp, mut gold, mut inventory, mut stash)) = player_data.iter_mut().find(|(owner, ..)| owner.0 == *client) else {
+                continue;
+            };
+
+            let roll = table.roll(&mut rng.0);
+            xp.value += roll.xp;
+            gold.value += roll.gold as u128;
+            give_items(&mut inventory, &mut stash, roll.items.iter().cloned(), &catalogue);
+
+            granted.write(ToClients {
+                mode: SendMode::Direct(*client),
+                event: LootGranted { xp: roll.xp, gold: roll.gold, items: roll.items },
+            });
+        }
+    }
+}
+
+fn loot_granted(
+    mut granted: EventReader<LootGranted>,
+    catalogue: Res<ItemCatalogue>,
+) {
+    for loot in granted.read() {
+        info!("Gained {} xp and {} gold", loot.xp, loot.gold);
+        for stack in loot.items.iter() {
+            let name = catalogue.0.get(&stack.id).map_or_else(|| stack.id.to_string(), |spec| spec.name.clone());
+            info!("Looted {} ×{}", name, stack.qty);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(item: Option<ItemId>, weight: u32, qty: (u16, u16)) -> LootEntry {
+        LootEntry { item, weight, qty }
+    }
+
+    fn table(rolls: u32, entries: Vec<LootEntry>) -> LootTable {
+        LootTable { name: "test".to_string(), xp: (0, 0), gold: (0, 0), rolls, entries }
+    }
+
+    fn rng(seed: u64) -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(seed)
+    }
+
+    #[test]
+    fn weighted_pick_follows_the_weights() {
+        let table = table(1, vec![entry(Some(1), 3, (1, 1)), entry(Some(2), 1, (1, 1))]);
+        let mut rng = rng(1);
+
+        let firsts = (0..4000).filter(|_| table.roll(&mut rng).items == vec![ItemStack { id: 1, qty: 1 }]).count();
+        assert!((2700..3300).contains(&firsts), "item 1 dropped {firsts} times out of 4000");
+    }
+
+    #[test]
+    fn zero_weights_never_drop() {
+        let mut rng = rng(2);
+
+        let empty = table(3, vec![entry(Some(1), 0, (1, 1)), entry(Some(2), 0, (1, 1))]);
+        assert!(empty.roll(&mut rng).items.is_empty());
+
+        let table = table(1, vec![entry(Some(1), 0, (1, 1)), entry(Some(2), 1, (1, 1)), entry(Some(3), 0, (1, 1))]);
+        for _ in 0..100 {
+            assert_eq!(table.roll(&mut rng).items, vec![ItemStack { id: 2, qty: 1 }]);
+        }
+    }
+
+    #[test]
+    fn nothing_entries_drop_nothing() {
+        let table = table(5, vec![entry(None, 1, (1, 1))]);
+        assert!(table.roll(&mut rng(3)).items.is_empty());
+    }
+
+    #[test]
+    fn repeated_drops_of_an_item_share_a_stack() {
+        let table = table(4, vec![entry(Some(1), 1, (2, 2))]);
+        assert_eq!(table.roll(&mut rng(4)).items, vec![ItemStack { id: 1, qty: 8 }]);
+    }
+
+    #[test]
+    fn ranges_include_both_ends() {
+        let mut rng = rng(5);
+        let rolls: Vec<u64> = (0..200).map(|_| roll_range(&mut rng, (1u64, 3u64))).collect();
+
+        assert!(rolls.iter().all(|value| (1..=3).contains(value)));
+        assert!(rolls.contains(&1) && rolls.contains(&3));
+        assert_eq!(roll_range(&mut rng, (5u16, 5u16)), 5);
+        // A range written the wrong way round falls back to its first value
+        assert_eq!(roll_range(&mut rng, (7u16, 2u16)), 7);
+    }
+
+    #[test]
+    fn the_same_seed_rolls_the_same_loot() {
+        let table = LootTable {
+            xp: (1, 100),
+            gold: (1, 100),
+            ..table(3, vec![entry(None, 2, (1, 1)), entry(Some(1), 1, (1, 5)), entry(Some(2), 1, (1, 5))])
+        };
+        let (mut first, mut second) = (rng(6), rng(6));
+
+        for _ in 0..20 {
+            let (a, b) = (table.roll(&mut first), table.roll(&mut second));
+            assert_eq!((a.xp, a.gold, a.items), (b.xp, b.gold, b.items));
+        }
+    }
+
+    #[test]
+    fn credit_needs_a_fair_share() {
+        let (big, small, fair) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+        let mut contributions = DamageContributions::default();
+        contributions.record(small, 5);
+        contributions.record(fair, 10);
+        contributions.record(big, 85);
+
+        let credited = contributions.credited();
+        assert!(credited.contains(&big));
+        assert!(credited.contains(&fair));
+        assert!(!credited.contains(&small));
+    }
+
+    #[test]
+    fn the_last_hit_always_counts() {
+        let (big, finisher) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut contributions = DamageContributions::default();
+        contributions.record(big, 99);
+        contributions.record(finisher, 1);
+
+        let mut credited = contributions.credited();
+        credited.sort();
+        assert_eq!(credited, vec![big, finisher]);
+        assert!(DamageContributions::default().credited().is_empty());
+    }
+}
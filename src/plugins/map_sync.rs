@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::components::character::Character;
+use crate::components::enemy::Enemy;
+use crate::components::humanoid::Position;
+use crate::components::island::{Chest, GenerateIsland, OnIsland};
+use crate::components::island_maps::{IslandMaps, MapResyncRequest, TerrainChange, TerrainChanged, TerrainType};
+use crate::components::overworld::Island;
+use crate::plugins::network::OwnedBy;
+use crate::preludes::network_preludes::*;
+
+/// Server side: send the full terrain of `island` to one client.
+#[derive(Event)]
+pub struct MapSnapshotRequest {
+    pub island: u64,
+    pub client_entity: Entity,
+}
+
+#[derive(Default)]
+struct PendingSnapshot {
+    version: u32,
+    chunks: Vec<(IVec3, Vec<(u16, TerrainType)>)>,
+}
+
+/// Snapshots that are still arriving, and islands we already asked a resync for.
+#[derive(Resource, Default)]
+struct MapSyncState {
+    pending: HashMap<u64, PendingSnapshot>,
+    resyncing: HashSet<u64>,
+}
+
+pub struct MapSyncPlugin;
+impl Plugin for MapSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<MapSyncState>()
+        .add_server_event::<MapUpdate>(Channel::Ordered)
+        .add_client_event::<MapResyncRequest>(Channel::Ordered)
+        .add_event::<MapSnapshotRequest>()
+        .add_event::<TerrainChange>()
+        .add_event::<TerrainChanged>()
+        .add_systems(Update, (
+            (resync_requests, send_map_snapshots, apply_terrain_changes).chain().run_if(server_running),
+            apply_map_updates.run_if(client_connected),
+        ));
+    }
+}
+
+fn resync_requests(
+    mut resync_events: EventReader<FromClient<MapResyncRequest>>,
+    mut snapshot_requests: EventWriter<MapSnapshotRequest>,
+    characters: Query<(&OwnedBy, &OnIsland), With<Character>>,
+) {
+    for FromClient { client_entity, event } in resync_events.read() {
+        // Only players standing on the island get to see its map
+        if characters.iter().any(|(owner, island)| owner.0 == *client_entity && island.0 == event.0) {
+            snapshot_requests.write(MapSnapshotRequest { island: event.0, client_entity: *client_entity });
+        }
+    }
+}
+
+fn send_map_snapshots(
+    mut snapshot_requests: EventReader<MapSnapshotRequest>,
+    mut map_updates: EventWriter<ToClients<MapUpdate>>,
+    island_maps: Res<IslandMaps>,
+) {
+    for request in snapshot_requests.read() {
+        // The host shares the server's maps
+        if request.client_entity == SERVER {
+            continue;
+        }
+
+        let Some(map) = island_maps.get_map(request.island) else {
+            continue;
+        };
+
+        let mut chunks = 0;
+        for coords in map.chunks.keys() {
+            map_updates.write(ToClients {
+                mode: SendMode::Direct(request.client_entity),
+                event: MapUpdate {
+                    island: request.island,
+                    version: map.version,
+                    update: UpdateType::Chunk { coords: *coords, terrain: map.chunk_terrain(*coords) },
+                },
+            });
+            chunks += 1;
+        }
+
+        map_updates.write(ToClients {
+            mode: SendMode::Direct(request.client_entity),
+            event: MapUpdate { island: request.island, version: map.version, update: UpdateType::SnapshotEnd { chunks } },
+        });
+    }
+}
+
+fn apply_terrain_changes(
+    mut changes: EventReader<TerrainChange>,
+    mut changed: EventWriter<TerrainChanged>,
+    mut map_updates: EventWriter<ToClients<MapUpdate>>,
+    mut island_maps: ResMut<IslandMaps>,
+    characters: Query<(&OwnedBy, &OnIsland), With<Character>>,
+) {
+    for change in changes.read() {
+        let Some(map) = island_maps.get_map_mut(change.island) else {
+            continue;
+        };
+
+        map.set_terrain(change.position, change.terrain);
+        map.version += 1;
+        changed.write(TerrainChanged { island: change.island, position: change.position });
+
+        for (owner, island) in characters.iter() {
+            if island.0 != change.island || owner.0 == SERVER {
+                continue;
+            }
+
+            map_updates.write(ToClients {
+                mode: SendMode::Direct(owner.0),
+                event: MapUpdate {
+                    island: change.island,
+                    version: map.version,
+                    update: UpdateType::Tile { position: change.position, terrain: change.terrain },
+                },
+            });
+        }
+    }
+}
+
+fn apply_map_updates(
+    mut commands: Commands,
+    mut map_updates: EventReader<MapUpdate>,
+    mut resync_requests: EventWriter<MapResyncRequest>,
+    mut changed: EventWriter<TerrainChanged>,
+    mut sync: ResMut<MapSyncState>,
+    mut island_maps: ResMut<IslandMaps>,
+    islands: Query<(Entity, &Island), With<GenerateIsland>>,
+    occupants: Query<(Entity, &Position, &OnIsland, Has<Character>), Or<(With<Character>, With<Enemy>, With<Chest>)>>,
+) {
+    for MapUpdate { island, version, update } in map_updates.read() {
+        match update {
+            UpdateType::Chunk { coords, terrain } => {
+                let pending = sync.pending.entry(*island).or_default();
+                if pending.version != *version {
+                    *pending = PendingSnapshot { version: *version, ..default() };
+                }
+                pending.chunks.push((*coords, terrain.clone()));
+            }
+            UpdateType::SnapshotEnd { chunks } => {
+                let pending = sync.pending.remove(island).unwrap_or_default();
+                if pending.version != *version || pending.chunks.len() != *chunks as usize {
+                    warn!("Incomplete snapshot for island {island}, asking for a resync");
+                    resync_requests.write(MapResyncRequest(*island));
+                    continue;
+                }
+
+                let mut map = Map::new();
+                map.version = *version;
+                for (coords, terrain) in pending.chunks.iter() {
+                    map.load_chunk_terrain(*coords, terrain);
+                }
+
+                // Entities were placed through position updates while the snapshot was arriving
+                for (entity, position, on_island, is_character) in occupants.iter() {
+                    if on_island.0 != *island {
+                        continue;
+                    }
+                    let kind = if is_character { TileType::Player } else { TileType::Enemy };
+                    map.add_entity_ivec3(position.0, Tile::new(kind, entity));
+                    map.entities.insert(entity);
+                }
+
+                island_maps.maps.insert(*island, map);
+                sync.resyncing.remove(island);
+
+                for (entity, island_id) in islands.iter() {
+                    if island_id.0 == *island {
+                        commands.entity(entity).remove::<GenerateIsland>();
+                    }
+                }
+            }
+            UpdateType::Tile { position, terrain } => {
+                let Some(map) = island_maps.get_map_mut(*island) else {
+                    continue;
+                };
+
+                if *version <= map.version {
+                    continue;
+                }
+
+                if *version != map.version + 1 {
+                    if sync.resyncing.insert(*island) {
+                        warn!("Missed map updates for island {island} ({} -> {version}), asking for a resync", map.version);
+                        resync_requests.write(MapResyncRequest(*island));
+                    }
+                    continue;
+                }
+
+                map.set_terrain(*position, *terrain);
+                map.version = *version;
+                changed.write(TerrainChanged { island: *island, position: *position });
+            }
+        }
+    }
+}
@@ -1,15 +1,28 @@
 pub mod network;
+pub mod visibility;
+pub mod auth;
+pub mod profile;
+pub mod network_conditions;
 pub mod enemy;
 pub mod island_controls;
 pub mod camera;
 pub mod humanoid;
 pub mod overworld;
 pub mod island;
+pub mod map_sync;
+pub mod world_state;
 pub mod ship;
 pub mod enemy_movement;
 pub mod enemy_behaviour;
 pub mod attack;
+pub mod lag_compensation;
 pub mod player;
+pub mod items;
+pub mod inventory;
+pub mod loot;
+pub mod progression;
+pub mod skills;
+pub mod shop;
 pub mod damage_numbers;
 pub mod ui;
 pub mod animations;
@@ -1,35 +1,97 @@
 use bevy::prelude::*;
-use bevy::winit::{UpdateMode::Continuous, WinitSettings};
 use bevy_replicon_renet2::netcode::ServerSetupConfig;
 use bevy_replicon_renet2::RenetChannelsExt;
 use serde::Deserialize;
 use serde::Serialize;
 use crate::components::island_maps::IslandMaps;
-use crate::components::overworld::{Ship, WorldSeed};
-use crate::components::character::LocalPlayer;
+use crate::components::island::OnIsland;
+use crate::components::humanoid::Position;
+use crate::components::overworld::{Ship, ShipSpawnPosition, WorldSeed};
+use crate::components::character::{Character, LocalPlayer};
+use crate::plugins::auth::{load_private_key, load_token, IssueTokenArgs};
+use crate::plugins::network_conditions::{NetworkConditions, SimulatedSocket};
+use crate::plugins::profile::{load_or_create_client_id, SavedProfile};
+use crate::plugins::world_state::WorldState;
 use crate::preludes::network_preludes::*;
 use crate::GameState;
 
-use clap::Parser;
+use clap::{Args, Parser};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Component, Clone, Copy, Serialize, Deserialize)]
 pub struct OwnedBy(pub Entity);
 
 #[derive(Event, Serialize, Deserialize)]
-struct ClientInfo;
+struct ClientInfo {
+    protocol_version: u64,
+}
+
+/// Netcode client id of the player behind a client entity, stable across reconnects.
+#[derive(Component, Clone, Copy)]
+pub struct PlayerId(pub u64);
+
+/// Island the local player was on before reconnecting, entered again once the overworld is up.
+#[derive(Resource, Clone, Copy)]
+pub struct ResumeIsland(pub u64);
+
+/// Overworld position saved in the host's profile, the host's ship starts there.
+#[derive(Resource, Clone, Copy)]
+pub struct ResumeShipPosition(pub Vec3);
+
+/// Entities of a disconnected player, kept until the grace period runs out.
+pub struct HeldSession {
+    pub timer: Timer,
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Resource)]
+pub struct HeldSessions {
+    pub grace: Duration,
+    pub sessions: HashMap<u64, HeldSession>,
+}
+
+impl Default for HeldSessions {
+    fn default() -> Self {
+        Self { grace: Duration::from_secs(RECONNECT_GRACE_SECONDS), sessions: HashMap::new() }
+    }
+}
+
+/// Game protocol version this peer speaks, checked when a client joins.
+#[derive(Resource, Clone, Copy)]
+pub struct ProtocolVersion(pub u64);
+
+/// Set on the client when it gave up connecting, holds a message for the player.
+#[derive(Resource)]
+pub struct ConnectionError(pub String);
+
+/// Put on a client the server turned away, it is disconnected once the timer runs out.
+/// The delay gives the rejection message time to reach the client.
+#[derive(Component)]
+pub struct PendingDisconnect(pub Timer);
+
+impl PendingDisconnect {
+    pub fn after(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
 
 
 pub struct NetworkPlugin;
 impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
         app
-        .insert_resource(WinitSettings {
-            focused_mode: Continuous,
-            unfocused_mode: Continuous,
-        })
-        .add_plugins((RepliconPlugins, RepliconRenetPlugins))
+        .add_plugins((
+            RepliconPlugins.set(ServerPlugin {
+                visibility_policy: VisibilityPolicy::Whitelist,
+                ..default()
+            }),
+            RepliconRenetPlugins,
+        ))
         .init_resource::<Cli>()
         .insert_resource(IslandMaps::new())
+        .init_resource::<HeldSessions>()
         .add_client_trigger::<ClientInfo>(Channel::Ordered)
         .add_server_trigger::<MakeLocal>(Channel::Ordered)
         .add_server_trigger::<GameInfo>(Channel::Unordered)
@@ -38,11 +100,14 @@ impl Plugin for NetworkPlugin {
         .add_observer(client_disconnected)
         .add_observer(make_local)
         .add_observer(game_info_trigger)
-        .add_server_event::<MapUpdate>(Channel::Ordered)
         .add_systems(Startup,
             read_cli.map(Result::unwrap)
         )
-        .add_systems(Update, client_request_info.run_if(client_just_connected));
+        .add_systems(Update, (
+            client_request_info.run_if(client_just_connected),
+            client_connection_check.run_if(in_state(GameState::Initializing).and(resource_exists::<RenetClient>)),
+            (expire_held_sessions, disconnect_rejected_clients).run_if(server_running),
+        ));
     }
 }
 
@@ -111,18 +176,80 @@ pub struct MakeLocal;
 #[derive(Event, Serialize, Deserialize)]
 pub struct GameInfo{
     seed: u64,
+    protocol_version: u64,
+    island: Option<u64>,
+}
+
+// Netcode handshake id. Kept fixed so clients of another version still reach the
+// protocol version check and get a readable error instead of a silent timeout.
+pub const PROTOCOL_ID: u64 = 0;
+pub const PROTOCOL_VERSION: u64 = 1;
+
+fn create_server(
+    channels: &RepliconChannels,
+    args: &ServerArgs,
+) -> Result<(RenetServer, NetcodeServerTransport), Box<dyn Error>> {
+    let server = RenetServer::new(ConnectionConfig::from_channels(
+        channels.server_configs(),
+        channels.client_configs(),
+    ));
+
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    let bind_addr = SocketAddr::new(args.bind, args.port);
+    let socket = UdpSocket::bind(bind_addr)?;
+    let authentication = match &args.private_key {
+        Some(path) => ServerAuthentication::Secure { private_key: load_private_key(path)? },
+        None => ServerAuthentication::Unsecure,
+    };
+    let server_config = ServerSetupConfig {
+        current_time,
+        max_clients: args.max_clients,
+        protocol_id: PROTOCOL_ID,
+        authentication,
+        socket_addresses: vec![args.public_addresses()],
+    };
+    let socket = NativeSocket::new(socket)?;
+    let transport = if args.conditions.is_enabled() {
+        warn!("Simulating network conditions: {:?}", args.conditions);
+        NetcodeServerTransport::new(server_config, SimulatedSocket::new(socket, args.conditions.clone()))?
+    } else {
+        NetcodeServerTransport::new(server_config, socket)?
+    };
+
+    Ok((server, transport))
+}
+
+/// Continues the world saved at `--world`, its seed replaces the random one.
+fn open_world(commands: &mut Commands, args: &ServerArgs, world_seed: &WorldSeed) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = &args.world {
+        let world = WorldState::open(path, world_seed.0)?;
+        info!("Loaded world {} with seed {}", path.display(), world.seed);
+        commands.insert_resource(WorldSeed(world.seed));
+        commands.insert_resource(world);
+    }
+    Ok(())
 }
 
 fn read_cli(
     mut commands: Commands,
     cli: Res<Cli>,
+    transport: Res<Transport>,
+    world_seed: Res<WorldSeed>,
     channels: Res<RepliconChannels>,
     mut state: ResMut<NextState<GameState>>
 ) -> Result<(), Box<dyn Error>> {
-    const PROTOCOL_ID: u64 = 0;
-
-    match *cli {
+    let in_memory = *transport == Transport::InMemory;
+    match &*cli {
+        // The test harness connects these in-process, no sockets or world file
+        Cli::Dedicated(args) if in_memory => {
+            commands.insert_resource(ProtocolVersion(args.protocol_version));
+            state.set(GameState::Overworld);
+        }
+        Cli::Client { protocol_version, .. } if in_memory => {
+            commands.insert_resource(ProtocolVersion(*protocol_version));
+        }
         Cli::SinglePlayer => {
+            commands.insert_resource(ProtocolVersion(PROTOCOL_VERSION));
             commands.spawn((
                 Ship,
                 OwnedBy(SERVER),
@@ -131,27 +258,14 @@ fn read_cli(
 
             state.set(GameState::Overworld);
         }
-        Cli::Server { port } => {
-            let server = RenetServer::new(ConnectionConfig::from_channels(
-                channels.server_configs(),
-                channels.client_configs(),
-            ));
-
-            let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-            let public_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
-            let socket = UdpSocket::bind(public_addr)?;
-            let server_config = ServerSetupConfig {
-                current_time,
-                max_clients: 10,
-                protocol_id: PROTOCOL_ID,
-                authentication: ServerAuthentication::Unsecure,
-                socket_addresses: vec![vec![public_addr]],
-            };
-            let transport = NetcodeServerTransport::new(server_config, NativeSocket::new(socket).unwrap())?;
-
+        Cli::Server(args) => {
+            let (server, transport) = create_server(&channels, args)?;
+            open_world(&mut commands, args, &world_seed)?;
 
             commands.insert_resource(server);
             commands.insert_resource(transport);
+            commands.insert_resource(ProtocolVersion(args.protocol_version));
+            commands.insert_resource(HeldSessions { grace: Duration::from_secs(args.reconnect_grace), ..default() });
 
             commands.spawn((Text::new("Server"),
                 TextFont {
@@ -169,27 +283,57 @@ fn read_cli(
 
             state.set(GameState::Overworld);
         }
-        Cli::Client { port, ip } => {
+        Cli::Dedicated(args) => {
+            let (server, transport) = create_server(&channels, args)?;
+            open_world(&mut commands, args, &world_seed)?;
+
+            commands.insert_resource(server);
+            commands.insert_resource(transport);
+            commands.insert_resource(ProtocolVersion(args.protocol_version));
+            commands.insert_resource(HeldSessions { grace: Duration::from_secs(args.reconnect_grace), ..default() });
+
+            info!("Dedicated server listening on {}:{} for up to {} clients", args.bind, args.port, args.max_clients);
+
+            state.set(GameState::Overworld);
+        }
+        Cli::Client { port, ip, protocol_version, token, profile, conditions } => {
             let client = RenetClient::new(
                 ConnectionConfig::from_channels(channels.server_configs(), channels.client_configs()),
                 false,
             );
 
             let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-            let client_id = current_time.as_millis() as u64;
-            let server_addr = SocketAddr::new(ip, port);
-            let socket = UdpSocket::bind((ip, 0))?;
-            let authentication = ClientAuthentication::Unsecure {
-                client_id,
-                protocol_id: PROTOCOL_ID,
-                socket_id: 0,
-                server_addr,
-                user_data: None,
+            let server_addr = SocketAddr::new(*ip, *port);
+            let bind_ip: IpAddr = if ip.is_ipv4() { Ipv4Addr::UNSPECIFIED.into() } else { Ipv6Addr::UNSPECIFIED.into() };
+            let socket = UdpSocket::bind((bind_ip, 0))?;
+            let (client_id, authentication) = match token {
+                Some(path) => {
+                    let connect_token = load_token(path)?;
+                    (connect_token.client_id, ClientAuthentication::Secure { connect_token })
+                }
+                None => {
+                    let client_id = load_or_create_client_id(profile)?;
+                    (client_id, ClientAuthentication::Unsecure {
+                        client_id,
+                        protocol_id: PROTOCOL_ID,
+                        socket_id: 0,
+                        server_addr,
+                        user_data: None,
+                    })
+                }
+            };
+            info!("Connecting to {server_addr} as client {client_id} (profile '{profile}')");
+            let socket = NativeSocket::new(socket)?;
+            let transport = if conditions.is_enabled() {
+                warn!("Simulating network conditions: {:?}", conditions);
+                NetcodeClientTransport::new(current_time, authentication, SimulatedSocket::new(socket, conditions.clone()))?
+            } else {
+                NetcodeClientTransport::new(current_time, authentication, socket)?
             };
-            let transport = NetcodeClientTransport::new(current_time, authentication, NativeSocket::new(socket).unwrap())?;
 
             commands.insert_resource(client);
             commands.insert_resource(transport);
+            commands.insert_resource(ProtocolVersion(*protocol_version));
 
             commands.spawn((
                 Text(format!("Client: {client_id}")),
@@ -202,23 +346,69 @@ fn read_cli(
 
             state.set(GameState::Initializing);
         }
+        Cli::GenerateKey { .. } | Cli::IssueToken(_) => {} // handled in main before the app is built
     }
 
     Ok(())
 }
 
 fn client_request_info(
-    mut commands: Commands
+    mut commands: Commands,
+    protocol_version: Res<ProtocolVersion>,
 ){
-    commands.client_trigger(ClientInfo);
+    commands.client_trigger(ClientInfo {
+        protocol_version: protocol_version.0,
+    });
+}
+
+fn client_connection_check(
+    mut commands: Commands,
+    client: Res<RenetClient>,
+    transport: Res<NetcodeClientTransport>,
+    error: Option<Res<ConnectionError>>,
+) {
+    if error.is_some() || !client.is_disconnected() {
+        return;
+    }
+
+    let reason = if let Some(reason) = transport.disconnect_reason() {
+        reason.to_string()
+    } else if let Some(reason) = client.disconnect_reason() {
+        reason.to_string()
+    } else {
+        "unknown reason".to_string()
+    };
+
+    error!("Could not connect to the server: {reason}");
+    commands.insert_resource(ConnectionError(format!("Could not connect to the server: {reason}")));
 }
 
 fn game_info_trigger(
     trigger: Trigger<GameInfo>,
     mut commands: Commands,
-    mut state: ResMut<NextState<GameState>>
+    mut state: ResMut<NextState<GameState>>,
+    protocol_version: Res<ProtocolVersion>,
+    client: Option<ResMut<RenetClient>>,
 ) {
+    if trigger.protocol_version != protocol_version.0 {
+        let message = format!(
+            "Version mismatch: server uses protocol {}, this client uses protocol {}",
+            trigger.protocol_version,
+            protocol_version.0
+        );
+        error!("{message}");
+        commands.insert_resource(ConnectionError(message));
+
+        if let Some(mut client) = client {
+            client.disconnect();
+        }
+        return;
+    }
+
     commands.insert_resource(WorldSeed(trigger.seed));
+    if let Some(island) = trigger.island {
+        commands.insert_resource(ResumeIsland(island));
+    }
     state.set(GameState::Overworld);
 }
 
@@ -232,60 +422,296 @@ fn make_local(
 fn client_connected(
     trigger: Trigger<FromClient<ClientInfo>>, 
     mut commands: Commands,
-    world_seed: Res<WorldSeed>
+    world_seed: Res<WorldSeed>,
+    protocol_version: Res<ProtocolVersion>,
+    mut held_sessions: ResMut<HeldSessions>,
+    world: Res<WorldState>,
+    clients: Query<(Entity, Option<&NetworkId>, Option<&PlayerId>, Has<PendingDisconnect>), With<ConnectedClient>>,
+    owned_query: Query<(Option<&OnIsland>, Has<Character>), With<OwnedBy>>,
 ) {
+    let Ok((_, network_id, player_id, rejected)) = clients.get(trigger.client_entity) else {
+        return;
+    };
+    // Already joined or turned away, a repeated info must not hand out another ship
+    if rejected || player_id.is_some() {
+        return;
+    }
+
     info!("{:?} connected", trigger.client_entity);
 
-    let boat_entity = commands.spawn((
-        Ship,
-        OwnedBy(trigger.target())
-    )).id();
+    if trigger.protocol_version != protocol_version.0 {
+        warn!(
+            "{:?} uses protocol {}, expected {}: rejecting",
+            trigger.client_entity,
+            trigger.protocol_version,
+            protocol_version.0
+        );
+
+        commands.entity(trigger.client_entity).insert(PendingDisconnect::after(REJECT_DISCONNECT_SECONDS));
+        commands.server_trigger(
+            ToClients {
+                mode: SendMode::Direct(trigger.client_entity),
+                event: GameInfo {
+                    seed: 0,
+                    protocol_version: protocol_version.0,
+                    island: None,
+                },
+            }
+        );
+        return;
+    }
 
+    // The id netcode authenticated, in secure mode it comes from the connect token
+    let Some(player_id) = network_id.map(|id| id.get()) else {
+        warn!("{:?} has no client id: rejecting", trigger.client_entity);
+        commands.entity(trigger.client_entity).insert(PendingDisconnect::after(0.0));
+        return;
+    };
+
+    if clients.iter().any(|(entity, _, other, _)| entity != trigger.client_entity && other.is_some_and(|other| other.0 == player_id)) {
+        warn!("Player {} is already connected: rejecting {:?}", player_id, trigger.client_entity);
+        commands.entity(trigger.client_entity).insert(PendingDisconnect::after(0.0));
+        return;
+    }
+
+    commands.entity(trigger.client_entity).insert(PlayerId(player_id));
+
+    let owned_entities = if let Some(session) = held_sessions.sessions.remove(&player_id) {
+        info!("Player {} resumed its session", player_id);
+        // Characters can die or be cleaned up while the player is away
+        let entities: Vec<Entity> = session.entities.into_iter().filter(|entity| owned_query.contains(*entity)).collect();
+        for entity in entities.iter() {
+            commands.entity(*entity).try_insert(OwnedBy(trigger.client_entity));
+        }
+        entities
+    } else {
+        // Items, progress and the ship position never come from the client, only from what this server kept for the player
+        let saved = world.players.get(&player_id).cloned().unwrap_or_else(SavedProfile::new);
+
+        let boat_entity = commands.spawn((
+            Ship,
+            OwnedBy(trigger.client_entity)
+        )).id();
+
+        if let Some(position) = saved.overworld_position.filter(|position| position.is_finite()) {
+            commands.entity(boat_entity).insert(ShipSpawnPosition(position));
+        }
+
+        let data_entity = commands.spawn((
+            saved.player_data(),
+            OwnedBy(trigger.client_entity),
+        )).id();
+
+        vec![boat_entity, data_entity]
+    };
+
+    let island = owned_entities.iter()
+        .find_map(|entity| owned_query.get(*entity).ok().filter(|(_, character)| *character).and_then(|(island, _)| island))
+        .map(|island| island.0);
 
     commands.server_trigger(
         ToClients {
             mode: SendMode::Direct(trigger.client_entity),
             event: GameInfo {
-                seed: world_seed.0
+                seed: world_seed.0,
+                protocol_version: protocol_version.0,
+                island,
             },
         }
     );
 
-    commands.server_trigger_targets(
-        ToClients {
-            mode: SendMode::Direct(trigger.client_entity),
-            event: MakeLocal,
-        },
-        boat_entity,
-    );
+    for entity in owned_entities {
+        commands.server_trigger_targets(
+            ToClients {
+                mode: SendMode::Direct(trigger.client_entity),
+                event: MakeLocal,
+            },
+            entity,
+        );
+    }
 }
 
 fn client_disconnected(
     trigger: Trigger<OnRemove, ConnectedClient>,
+    player_ids: Query<&PlayerId>,
+    owned_query: Query<(Entity, &OwnedBy)>,
+    ships: Query<&Transform, With<Ship>>,
+    mut held_sessions: ResMut<HeldSessions>,
+    mut world: ResMut<WorldState>,
 ) {
-    info!("{:?} disconnected", trigger.target());
+    let client_entity = trigger.target();
+    info!("{:?} disconnected", client_entity);
+
+    let Ok(player_id) = player_ids.get(client_entity) else {
+        return;
+    };
+
+    let entities: Vec<Entity> = owned_query
+        .iter()
+        .filter(|(_, owner)| owner.0 == client_entity)
+        .map(|(entity, _)| entity)
+        .collect();
+
+    if let Some(transform) = entities.iter().find_map(|entity| ships.get(*entity).ok()) {
+        world.players.entry(player_id.0).or_insert_with(SavedProfile::new).overworld_position = Some(transform.translation);
+    }
+
+    info!("Holding {} entities of player {} for {:?}", entities.len(), player_id.0, held_sessions.grace);
+    let timer = Timer::new(held_sessions.grace, TimerMode::Once);
+    held_sessions.sessions.insert(player_id.0, HeldSession { timer, entities });
+}
+
+fn expire_held_sessions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut held_sessions: ResMut<HeldSessions>,
+    mut island_maps: ResMut<IslandMaps>,
+    characters: Query<(&Position, &OnIsland), With<Character>>,
+) {
+    held_sessions.sessions.retain(|player_id, session| {
+        if !session.timer.tick(time.delta()).finished() {
+            return true;
+        }
+
+        info!("Session of player {} expired: cleaning up", player_id);
+        for entity in session.entities.iter() {
+            if let Ok((position, island)) = characters.get(*entity) {
+                if let Some(map) = island_maps.get_map_mut(island.0) {
+                    map.remove_player(position.0, *entity);
+                }
+            }
+
+            if let Ok(mut entity_commands) = commands.get_entity(*entity) {
+                entity_commands.despawn();
+            }
+        }
+        false
+    });
+}
+
+fn disconnect_rejected_clients(
+    time: Res<Time>,
+    mut clients: Query<(Entity, &mut PendingDisconnect)>,
+    mut disconnects: EventWriter<DisconnectRequest>,
+) {
+    for (client_entity, mut pending) in clients.iter_mut() {
+        if pending.0.tick(time.delta()).just_finished() {
+            info!("Disconnecting rejected {:?}", client_entity);
+            disconnects.write(DisconnectRequest { client: client_entity });
+        }
+    }
 }
 
 const PORT: u16 = 5000;
+const MAX_CLIENTS: usize = 10;
+const RECONNECT_GRACE_SECONDS: u64 = 60;
+const REJECT_DISCONNECT_SECONDS: f32 = 1.0;
+const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Args, PartialEq, Clone)]
+pub struct ServerArgs {
+    #[arg(short, long, default_value_t = PORT)]
+    pub port: u16,
+
+    /// Local address to bind, use 0.0.0.0 to accept connections from the LAN
+    #[arg(short, long, default_value_t = Ipv4Addr::LOCALHOST.into())]
+    pub bind: IpAddr,
+
+    /// Addresses clients use to reach this server, can be given multiple times
+    #[arg(long = "public-addr")]
+    pub public_addr: Vec<SocketAddr>,
+
+    #[arg(short, long, default_value_t = MAX_CLIENTS)]
+    pub max_clients: usize,
+
+    #[arg(long, default_value_t = PROTOCOL_VERSION)]
+    pub protocol_version: u64,
+
+    /// Private key file, enables secure mode where clients need a connect token
+    #[arg(short = 'k', long)]
+    pub private_key: Option<PathBuf>,
+
+    /// Seconds a disconnected player's ship and character are kept for a reconnect
+    #[arg(long, default_value_t = RECONNECT_GRACE_SECONDS)]
+    pub reconnect_grace: u64,
+
+    /// World save file, created on first start. Without it island progress is lost on shutdown
+    #[arg(long)]
+    pub world: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub conditions: NetworkConditions,
+}
+
+impl ServerArgs {
+    /// Falls back to the bind address, or loopback when bound to all interfaces.
+    pub fn public_addresses(&self) -> Vec<SocketAddr> {
+        if !self.public_addr.is_empty() {
+            return self.public_addr.clone();
+        }
+
+        let ip = if self.bind.is_unspecified() { Ipv4Addr::LOCALHOST.into() } else { self.bind };
+        vec![SocketAddr::new(ip, self.port)]
+    }
+}
+
+/// How the peers reach each other, set on the `SimulationPlugin`.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Transport {
+    #[default]
+    Sockets,
+    /// No sockets, the test harness exchanges the messages in-process
+    InMemory,
+}
 
 #[derive(Parser, PartialEq, Resource)]
-enum Cli {
+pub enum Cli {
     SinglePlayer,
-    Server {
-        #[arg(short, long, default_value_t = PORT)]
-        port: u16,
-    },
+    Server(ServerArgs),
+    /// Headless server without a window or a local player
+    Dedicated(ServerArgs),
     Client {
         #[arg(short, long, default_value_t = Ipv4Addr::LOCALHOST.into())]
         ip: IpAddr,
 
         #[arg(short, long, default_value_t = PORT)]
         port: u16,
+
+        #[arg(long, default_value_t = PROTOCOL_VERSION)]
+        protocol_version: u64,
+
+        /// Connect token issued by the server host, required when the server runs in secure mode
+        #[arg(short, long)]
+        token: Option<PathBuf>,
+
+        /// Player profile, each profile keeps its own stable client id
+        #[arg(long, default_value = DEFAULT_PROFILE)]
+        profile: String,
+
+        #[command(flatten)]
+        conditions: NetworkConditions,
     },
+    /// Write a new random private key for secure mode
+    GenerateKey {
+        #[arg(default_value = "server.key")]
+        out: PathBuf,
+    },
+    /// Issue a connect token for a client, signed with the server private key
+    IssueToken(IssueTokenArgs),
 }
 
 impl Default for Cli {
     fn default() -> Self {
         Self::parse()
     }
+}
+
+impl Cli {
+    /// Profile the local player's progress is saved to, hosts always use the default one.
+    pub fn profile(&self) -> &str {
+        match self {
+            Cli::Client { profile, .. } => profile,
+            _ => DEFAULT_PROFILE,
+        }
+    }
 }
\ No newline at end of file
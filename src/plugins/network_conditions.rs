@@ -0,0 +1,284 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use bevy_replicon_renet2::netcode::{ClientSocket, NetcodeTransportError, ServerSocket};
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Artificial network conditions for testing, every value applies to each direction separately.
+#[derive(Args, PartialEq, Clone, Debug, Default)]
+pub struct NetworkConditions {
+    /// Added one-way delay in milliseconds
+    #[arg(long = "sim-latency", default_value_t = 0)]
+    pub latency_ms: u64,
+
+    /// Random extra delay of up to this many milliseconds, also reorders packets
+    #[arg(long = "sim-jitter", default_value_t = 0)]
+    pub jitter_ms: u64,
+
+    /// Chance between 0 and 1 that a packet is dropped
+    #[arg(long = "sim-loss", default_value_t = 0.0)]
+    pub loss: f64,
+
+    /// Chance between 0 and 1 that a packet is delivered twice
+    #[arg(long = "sim-duplicate", default_value_t = 0.0)]
+    pub duplicate: f64,
+}
+
+impl NetworkConditions {
+    pub fn is_enabled(&self) -> bool {
+        self.latency_ms > 0 || self.jitter_ms > 0 || self.loss > 0.0 || self.duplicate > 0.0
+    }
+
+    fn delay(&self, rng: &mut impl Rng) -> Duration {
+        let jitter = if self.jitter_ms > 0 { rng.random_range(0..=self.jitter_ms) } else { 0 };
+        Duration::from_millis(self.latency_ms + jitter)
+    }
+
+    fn lost(&self, rng: &mut impl Rng) -> bool {
+        self.loss > 0.0 && rng.random_bool(self.loss.clamp(0.0, 1.0))
+    }
+
+    fn duplicated(&self, rng: &mut impl Rng) -> bool {
+        self.duplicate > 0.0 && rng.random_bool(self.duplicate.clamp(0.0, 1.0))
+    }
+}
+
+#[derive(Debug)]
+struct DelayedPacket {
+    due: Instant,
+    addr: SocketAddr,
+    payload: Vec<u8>,
+}
+
+/// Wraps a netcode socket and applies [`NetworkConditions`] to everything going through it.
+/// Works on loopback, so a server and clients on one machine behave like they are far apart.
+#[derive(Debug)]
+pub struct SimulatedSocket<S> {
+    inner: S,
+    conditions: NetworkConditions,
+    rng: StdRng,
+    incoming: Vec<DelayedPacket>,
+    outgoing: Vec<DelayedPacket>,
+}
+
+impl<S> SimulatedSocket<S> {
+    pub fn new(inner: S, conditions: NetworkConditions) -> Self {
+        Self { inner, conditions, rng: StdRng::from_os_rng(), incoming: Vec::new(), outgoing: Vec::new() }
+    }
+
+    fn schedule(conditions: &NetworkConditions, rng: &mut StdRng, queue: &mut Vec<DelayedPacket>, addr: SocketAddr, payload: &[u8]) {
+        if conditions.lost(rng) {
+            return;
+        }
+
+        let now = Instant::now();
+        queue.push(DelayedPacket { due: now + conditions.delay(rng), addr, payload: payload.to_vec() });
+
+        if conditions.duplicated(rng) {
+            queue.push(DelayedPacket { due: now + conditions.delay(rng), addr, payload: payload.to_vec() });
+        }
+    }
+
+    // Takes the earliest packet that is due, so jitter can reorder packets like a real network
+    fn pop_due(queue: &mut Vec<DelayedPacket>) -> Option<DelayedPacket> {
+        let now = Instant::now();
+        let index = queue.iter()
+            .enumerate()
+            .filter(|(_, packet)| packet.due <= now)
+            .min_by_key(|(_, packet)| packet.due)
+            .map(|(index, _)| index)?;
+
+        Some(queue.swap_remove(index))
+    }
+}
+
+fn would_block() -> io::Error {
+    io::Error::from(io::ErrorKind::WouldBlock)
+}
+
+fn is_would_block(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::WouldBlock
+}
+
+// Shared by the server and client socket implementations, they only differ in the trait they forward to
+macro_rules! simulated_io {
+    ($trait:ident) => {
+        fn preupdate(&mut self) {
+            $trait::preupdate(&mut self.inner);
+        }
+
+        fn try_recv(&mut self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+            loop {
+                match $trait::try_recv(&mut self.inner, buffer) {
+                    Ok((len, addr)) => Self::schedule(&self.conditions, &mut self.rng, &mut self.incoming, addr, &buffer[..len]),
+                    Err(error) if is_would_block(&error) => break,
+                    Err(error) => return Err(error),
+                }
+            }
+
+            let Some(packet) = Self::pop_due(&mut self.incoming) else {
+                return Err(would_block());
+            };
+
+            let len = packet.payload.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&packet.payload[..len]);
+            Ok((len, packet.addr))
+        }
+
+        fn postupdate(&mut self) {
+            while let Some(packet) = Self::pop_due(&mut self.outgoing) {
+                if let Err(error) = $trait::send(&mut self.inner, packet.addr, &packet.payload) {
+                    log::warn!("Simulated socket failed to send a delayed packet: {error}");
+                }
+            }
+            $trait::postupdate(&mut self.inner);
+        }
+
+        fn send(&mut self, addr: SocketAddr, packet: &[u8]) -> Result<(), NetcodeTransportError> {
+            Self::schedule(&self.conditions, &mut self.rng, &mut self.outgoing, addr, packet);
+            Ok(())
+        }
+    };
+}
+
+impl<S: ServerSocket + std::fmt::Debug> ServerSocket for SimulatedSocket<S> {
+    fn is_encrypted(&self) -> bool {
+        ServerSocket::is_encrypted(&self.inner)
+    }
+
+    fn is_reliable(&self) -> bool {
+        ServerSocket::is_reliable(&self.inner)
+    }
+
+    fn addr(&self) -> io::Result<SocketAddr> {
+        ServerSocket::addr(&self.inner)
+    }
+
+    fn is_closed(&mut self) -> bool {
+        ServerSocket::is_closed(&mut self.inner)
+    }
+
+    fn close(&mut self) {
+        ServerSocket::close(&mut self.inner)
+    }
+
+    fn connection_denied(&mut self, addr: SocketAddr) {
+        self.inner.connection_denied(addr)
+    }
+
+    fn connection_accepted(&mut self, client_id: u64, addr: SocketAddr) {
+        self.inner.connection_accepted(client_id, addr)
+    }
+
+    fn disconnect(&mut self, addr: SocketAddr) {
+        self.inner.disconnect(addr)
+    }
+
+    simulated_io!(ServerSocket);
+}
+
+impl<S: ClientSocket + std::fmt::Debug> ClientSocket for SimulatedSocket<S> {
+    fn is_encrypted(&self) -> bool {
+        ClientSocket::is_encrypted(&self.inner)
+    }
+
+    fn is_reliable(&self) -> bool {
+        ClientSocket::is_reliable(&self.inner)
+    }
+
+    fn addr(&self) -> io::Result<SocketAddr> {
+        ClientSocket::addr(&self.inner)
+    }
+
+    fn is_closed(&mut self) -> bool {
+        ClientSocket::is_closed(&mut self.inner)
+    }
+
+    fn close(&mut self) {
+        ClientSocket::close(&mut self.inner)
+    }
+
+    simulated_io!(ClientSocket);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Socket = SimulatedSocket<()>;
+
+    const ADDR: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST), 5000);
+
+    /// Schedules `count` numbered packets, returns the queue.
+    fn send(conditions: &NetworkConditions, count: u8) -> Vec<DelayedPacket> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut queue = Vec::new();
+        for payload in 0..count {
+            Socket::schedule(conditions, &mut rng, &mut queue, ADDR, &[payload]);
+        }
+        queue
+    }
+
+    fn drain(queue: &mut Vec<DelayedPacket>) -> Vec<DelayedPacket> {
+        std::iter::from_fn(|| Socket::pop_due(queue)).collect()
+    }
+
+    #[test]
+    fn is_disabled_by_default() {
+        assert!(!NetworkConditions::default().is_enabled());
+        assert!(NetworkConditions { jitter_ms: 1, ..Default::default() }.is_enabled());
+        assert!(NetworkConditions { duplicate: 0.1, ..Default::default() }.is_enabled());
+    }
+
+    #[test]
+    fn no_conditions_pass_packets_through_unchanged() {
+        let conditions = NetworkConditions::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut queue = Vec::new();
+
+        for payload in 0..20u8 {
+            Socket::schedule(&conditions, &mut rng, &mut queue, ADDR, &[payload, 1, 2]);
+            let packet = Socket::pop_due(&mut queue).expect("packet was held back");
+            assert_eq!((packet.addr, packet.payload), (ADDR, vec![payload, 1, 2]));
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn packets_come_out_in_due_time_order_within_the_jitter() {
+        let conditions = NetworkConditions { latency_ms: 5, jitter_ms: 20, ..Default::default() };
+        let before = Instant::now();
+        let mut queue = send(&conditions, 50);
+        let after = Instant::now();
+
+        assert!(Socket::pop_due(&mut queue).is_none(), "packet came out before the latency");
+        for packet in queue.iter() {
+            assert!(packet.due >= before + Duration::from_millis(5));
+            assert!(packet.due <= after + Duration::from_millis(25));
+        }
+
+        std::thread::sleep(Duration::from_millis(30));
+        let packets = drain(&mut queue);
+        assert_eq!(packets.len(), 50);
+        assert!(packets.windows(2).all(|pair| pair[0].due <= pair[1].due));
+    }
+
+    #[test]
+    fn full_loss_drops_everything() {
+        let conditions = NetworkConditions { loss: 1.0, ..Default::default() };
+        assert!(send(&conditions, 50).is_empty());
+    }
+
+    #[test]
+    fn full_duplication_delivers_every_packet_twice() {
+        let conditions = NetworkConditions { duplicate: 1.0, ..Default::default() };
+        let mut queue = send(&conditions, 50);
+
+        let mut payloads: Vec<u8> = drain(&mut queue).into_iter().map(|packet| packet.payload[0]).collect();
+        payloads.sort();
+        assert_eq!(payloads, (0..50).flat_map(|payload| [payload, payload]).collect::<Vec<_>>());
+    }
+}
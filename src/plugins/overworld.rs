@@ -3,6 +3,7 @@ use bevy::render::view::RenderLayers;
 use crate::components::island::{EnteredIsland, GenerateIsland, VisualizeIsland};
 use crate::components::character::LocalPlayer;
 use crate::islands::atoll::Atoll;
+use crate::plugins::network::ResumeIsland;
 use crate::GameState;
 use crate::components::overworld::*;
 use crate::plugins::camera::{CameraTarget, NewCameraTarget, LAYER_WATER};
@@ -36,19 +37,31 @@ impl Material for WaterMaterial {
 }
 
 pub const WATER_HEIGHT : f32 = 0.3;
+pub const OCEAN_BOUNDS : f32 = 40.0;
 pub struct OverworldPlugin;
 
 impl Plugin for OverworldPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(OnEnter(GameState::Overworld), spawn_overworld);
+    }
+}
+
+pub struct OverworldPresentationPlugin;
+
+impl Plugin for OverworldPresentationPlugin {
     fn build(&self, app: &mut App) {
         app
         .add_plugins((MeshPickingPlugin, MaterialPlugin::<WaterMaterial>::default()))
-        .add_systems(OnEnter(GameState::Overworld), (spawn_overworld, activate_overworld, spawn_overworld_ui))
+        .add_systems(OnEnter(GameState::Overworld), (spawn_ocean, activate_overworld, spawn_overworld_ui))
         .add_systems(OnExit(GameState::Overworld), overworld_cleanup)
         .add_systems(
             Update,
             (
+                visualize_islands,
                 move_ocean,
                 island_proximity.run_if(in_state(GameState::Overworld)),
+                resume_island.run_if(in_state(GameState::Overworld).and(resource_exists::<ResumeIsland>)),
             )
         );
     }
@@ -138,9 +151,6 @@ fn poisson_disk_sample_2d(
 
 fn spawn_overworld(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut water_materials: ResMut<Assets<WaterMaterial>>,
     overworld_query: Query<&OverworldRoot>,
     world_seed: Res<WorldSeed>,
 ) {
@@ -157,34 +167,16 @@ fn spawn_overworld(
         ))
         .id();
 
-    // ocean
-    commands
-        .spawn((
-            Mesh3d(meshes.add(Plane3d::default().mesh().size(75.0, 75.0).subdivisions(500))),
-            MeshMaterial3d(water_materials.add(WaterMaterial {
-                ..Default::default()
-            })),
-            Transform::from_xyz(0.0, WATER_HEIGHT, 0.0),
-            Ocean,
-            RenderLayers::layer(LAYER_WATER.into()),
-        ))
-        .observe(on_clicked_ocean);
-
     // starter island
     commands
         .spawn((
-            Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::srgb_u8(100, 255, 100),
-                ..Default::default()
-            })),
             Transform::from_xyz(0.0, 0.2, 0.0),
             StarterIsland,
             Island(0),
+            IslandTint(Color::srgb_u8(100, 255, 100)),
             Visibility::Inherited,
             Atoll
         ))
-        .observe(on_clicked_island)
         .insert(ChildOf(overworld_root));
 
     let mut rng = ChaCha8Rng::seed_from_u64(world_seed.0);
@@ -208,21 +200,59 @@ fn spawn_overworld(
         // Spawn the island entity
         commands
         .spawn((
-            Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color,
-                ..Default::default()
-            })),
             Transform::from_xyz(pos.x, 0.2, pos.y),
             Visibility::Inherited, 
             Island((i + 1) as u64),
+            IslandTint(base_color),
             island_type
         ))
-        .observe(on_clicked_island)
         .insert(ChildOf(overworld_root));
     }
 }
 
+fn spawn_ocean(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut water_materials: ResMut<Assets<WaterMaterial>>,
+    ocean_query: Query<&Ocean>,
+) {
+    if ocean_query.single().is_ok() {
+        return;
+    }
+
+    commands
+        .spawn((
+            Mesh3d(meshes.add(Plane3d::default().mesh().size(75.0, 75.0).subdivisions(500))),
+            MeshMaterial3d(water_materials.add(WaterMaterial {
+                ..Default::default()
+            })),
+            Transform::from_xyz(0.0, WATER_HEIGHT, 0.0),
+            Ocean,
+            RenderLayers::layer(LAYER_WATER.into()),
+        ))
+        .observe(on_clicked_ocean);
+}
+
+fn visualize_islands(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    islands: Query<(Entity, &IslandTint), Without<Mesh3d>>,
+) {
+    for (entity, tint) in islands.iter() {
+        commands
+            .entity(entity)
+            .insert((
+                Mesh3d(meshes.add(Cuboid::new(1.0, 1.0, 1.0))),
+                MeshMaterial3d(materials.add(StandardMaterial {
+                    base_color: tint.0,
+                    ..Default::default()
+                })),
+            ))
+            .observe(on_clicked_island);
+    }
+}
+
 fn move_ocean(
     mut ocean: Query<&mut Transform, With<Ocean>>,
     target: Query<&Transform, (With<CameraTarget>, Without<Ocean>)>
@@ -291,6 +321,24 @@ fn island_proximity(
     }
 }
 
+fn resume_island(
+    mut commands: Commands,
+    resume: Res<ResumeIsland>,
+    island_query: Query<(Entity, &Island)>,
+    mut state: ResMut<NextState<GameState>>,
+    mut player_enter_event: EventWriter<EnteredIsland>
+) {
+    let Some((island_entity, _)) = island_query.iter().find(|(_, island)| island.0 == resume.0) else {
+        return;
+    };
+
+    info!("Resuming on island {}", resume.0);
+    commands.entity(island_entity).insert(LocalIsland).insert(GenerateIsland).insert(VisualizeIsland);
+    player_enter_event.write(EnteredIsland(resume.0));
+    commands.remove_resource::<ResumeIsland>();
+    state.set(GameState::Island);
+}
+
 fn on_clicked_island(
     click: Trigger<Pointer<Click>>,
     mut commands: Commands, 
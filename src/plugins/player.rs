@@ -1,67 +1,109 @@
 use bevy::prelude::*;
 
-use crate::components::player::{CharacterXp, Gold, Inventory, ItemStack, RewardEvent};
+use crate::components::character::LocalPlayer;
+use crate::components::island::LeaveIsland;
+use crate::components::overworld::Ship;
+use crate::components::player::{CharacterXp, Equipment, Gold, Inventory, PlayerData, SaveEvent, SkillLoadout, Stash, UnlockedSkills};
+use crate::plugins::network::{Cli, OwnedBy, ResumeShipPosition};
+use crate::preludes::network_preludes::*;
+use crate::plugins::profile::{load_profile, save_profile, PlayerDataQuery, SaveThrottle, SavedProfile};
+
+/// Name of the profile the local player's progress is loaded from and saved to.
+#[derive(Resource)]
+pub struct ActiveProfile(pub String);
+
+#[derive(Resource, Default)]
+struct ProfileSaves(SaveThrottle);
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
+        .init_resource::<ProfileSaves>()
         .add_systems(Startup, load_player)
-        .add_observer(reward_trigger);
+        .add_systems(Update, (save_on_leave_island, (mark_progress_changed, flush_profile).chain()))
+        .add_observer(save_trigger);
     }
 }
 
 fn load_player(
-    mut commands: Commands
+    mut commands: Commands,
+    cli: Res<Cli>,
 ){
-    commands.spawn(CharacterXp{value: 0, level: 0});
-    commands.spawn(Gold{value: 0});
-    commands.spawn(Inventory::default());
+    // Items and progress live on the server, a host keeps its own save, a client's is kept by the server it joins
+    if !matches!(*cli, Cli::SinglePlayer | Cli::Server(_)) {
+        return;
+    }
+
+    let profile = cli.profile().to_string();
+    let (saved, writable) = match load_profile(&profile) {
+        Ok(saved) => (saved.unwrap_or_else(SavedProfile::new), true),
+        Err(error) => {
+            // Keep the unreadable file around instead of overwriting it with a fresh profile
+            error!("Could not load profile '{profile}': {error}, progress will not be saved");
+            (SavedProfile::new(), false)
+        }
+    };
+
+    if let Some(position) = saved.overworld_position {
+        commands.insert_resource(ResumeShipPosition(position));
+    }
+
+    if writable {
+        commands.insert_resource(ActiveProfile(profile));
+    }
+
+    commands.spawn((
+        saved.player_data(),
+        OwnedBy(SERVER),
+        LocalPlayer,
+    ));
 }
 
-fn reward_trigger(
-    trigger: Trigger<RewardEvent>,
-    mut xp_query: Query<&mut CharacterXp>,
-    mut gold_query: Query<&mut Gold>,
-    mut inventory_query: Query<&mut Inventory>
+fn save_trigger(
+    _trigger: Trigger<SaveEvent>,
+    profile: Option<Res<ActiveProfile>>,
+    mut saves: ResMut<ProfileSaves>,
+    data_query: Query<PlayerDataQuery, (With<PlayerData>, With<LocalPlayer>)>,
+    ship_query: Query<&Transform, (With<Ship>, With<LocalPlayer>)>,
 ) {
-    if let Ok(mut char_xp) = xp_query.single_mut() {
-        char_xp.value += trigger.xp;
-    } 
-
-    if let Ok(mut player_gold) = gold_query.single_mut() {
-        player_gold.value += trigger.gold as u128;
-    } 
+    let Some(profile) = profile else { return };
+    saves.0.take();
+    let Ok(data) = data_query.single() else {
+        return;
+    };
 
-    if trigger.items.is_some() {
-        if let Ok(mut player_inv) = inventory_query.single_mut() {
-            for item in trigger.items.as_ref().unwrap().iter() {
-                let mut empty_slot: Option<&mut Option<ItemStack>> = None;
-                let mut added = false;
+    let saved = SavedProfile::from_player_data(data, ship_query.single().ok().map(|transform| transform.translation));
+    if let Err(error) = save_profile(&profile.0, &saved) {
+        error!("Could not save profile '{}': {error}", profile.0);
+    }
+}
 
-                for slot in &mut player_inv.slots {
-                    match slot {
-                        Some(stack) if stack.id == item.id => {
-                            stack.qty += item.qty;
-                            added = true;
-                            break;
-                        }
-                        None if empty_slot.is_none() => {
-                            empty_slot = Some(slot);
-                        }
-                        _ => {}
-                    }
-                }
+fn save_on_leave_island(
+    mut commands: Commands,
+    mut leave_island_event: EventReader<LeaveIsland>,
+) {
+    if leave_island_event.read().count() > 0 {
+        commands.trigger(SaveEvent);
+    }
+}
 
-                if !added {
-                    if let Some(slot) = empty_slot {
-                        *slot = Some(ItemStack { id: item.id, qty: item.qty });
-                    } else {
-                        println!("No empty inventory slot for item {}", item.id);
-                    }
-                }
-            }
-        } 
+// The server hands out rewards and changes the items, the save follows a few seconds later
+fn mark_progress_changed(
+    mut saves: ResMut<ProfileSaves>,
+    data: Query<(), (With<PlayerData>, With<LocalPlayer>, Or<(Changed<CharacterXp>, Changed<Gold>, Changed<Inventory>, Changed<Stash>, Changed<Equipment>, Changed<UnlockedSkills>, Changed<SkillLoadout>)>)>,
+) {
+    if !data.is_empty() {
+        saves.0.mark_dirty();
     }
 }
 
+fn flush_profile(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut saves: ResMut<ProfileSaves>,
+) {
+    if saves.0.tick(time.delta()) {
+        commands.trigger(SaveEvent);
+    }
+}
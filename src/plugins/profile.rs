@@ -0,0 +1,219 @@
+use bevy::math::Vec3;
+use bevy::prelude::Bundle;
+use bevy::time::{Timer, TimerMode};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::components::player::{CharacterXp, Equipment, Gold, Inventory, ItemStack, PlayerData, SkillLoadout, Stash, UnlockedSkills, INVENTORY_SLOTS};
+use crate::plugins::attack::AttackId;
+
+const APP_DIR: &str = "DiceVenture";
+const CLIENT_ID_FILE: &str = "client_id";
+const PROFILE_FILE: &str = "profile.ron";
+const SAVE_INTERVAL_SECONDS: f32 = 5.0;
+
+/// Bump whenever [`SavedProfile`] changes shape and teach [`migrate`] about the old layout.
+pub const PROFILE_VERSION: u32 = 1;
+
+/// Components of a `PlayerData` entity that end up in a save.
+pub type PlayerDataQuery = (
+    &'static CharacterXp,
+    &'static Gold,
+    &'static Inventory,
+    &'static Stash,
+    &'static Equipment,
+    &'static UnlockedSkills,
+    &'static SkillLoadout,
+);
+
+/// Player progress as written to disk.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct SavedProfile {
+    pub version: u32,
+    pub xp: u64,
+    pub level: u64,
+    pub gold: u128,
+    pub inventory: Vec<Option<ItemStack>>,
+    pub stash: Vec<ItemStack>,
+    pub equipment: Equipment,
+    pub unlocked_skills: Vec<AttackId>,
+    pub skill_points_spent: u32,
+    pub skill_loadout: Vec<Option<AttackId>>,
+    pub overworld_position: Option<Vec3>,
+}
+
+impl SavedProfile {
+    pub fn new() -> Self {
+        // Starter skills come from the skill tree once the server has the player
+        SavedProfile { version: PROFILE_VERSION, level: 1, ..Default::default() }
+    }
+
+    /// Snapshot of a `PlayerData` entity, as read by [`PlayerDataQuery`].
+    pub fn from_player_data(
+        (xp, gold, inventory, stash, equipment, skills, loadout): (&CharacterXp, &Gold, &Inventory, &Stash, &Equipment, &UnlockedSkills, &SkillLoadout),
+        overworld_position: Option<Vec3>,
+    ) -> Self {
+        SavedProfile {
+            version: PROFILE_VERSION,
+            xp: xp.value,
+            level: xp.level,
+            gold: gold.value,
+            inventory: inventory.slots.clone(),
+            stash: stash.items.clone(),
+            equipment: equipment.clone(),
+            unlocked_skills: skills.skills.clone(),
+            skill_points_spent: skills.points_spent,
+            skill_loadout: loadout.0.to_vec(),
+            overworld_position,
+        }
+    }
+
+    /// The server side `PlayerData` entity restored from this save.
+    pub fn player_data(self) -> impl Bundle {
+        let mut inventory = Inventory::from_slots(self.inventory);
+        inventory.slots.truncate(INVENTORY_SLOTS);
+
+        (
+            PlayerData,
+            inventory,
+            Stash { items: self.stash },
+            self.equipment,
+            CharacterXp { value: self.xp, level: self.level },
+            Gold { value: self.gold },
+            SkillLoadout::from_saved(&self.skill_loadout, &self.unlocked_skills),
+            UnlockedSkills { skills: self.unlocked_skills, points_spent: self.skill_points_spent },
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileHeader {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Per-user data directory, e.g. `~/.local/share/DiceVenture` or `%APPDATA%\DiceVenture`.
+pub fn data_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR)
+}
+
+pub fn profile_dir(profile: &str) -> PathBuf {
+    data_dir().join("profiles").join(profile)
+}
+
+/// Returns the client id of a profile, creating a random one the first time the profile is used.
+pub fn load_or_create_client_id(profile: &str) -> io::Result<u64> {
+    let dir = profile_dir(profile);
+    let path = dir.join(CLIENT_ID_FILE);
+
+    if let Ok(contents) = fs::read_to_string(&path) {
+        if let Ok(id) = contents.trim().parse::<u64>() {
+            return Ok(id);
+        }
+        bevy::log::warn!("Corrupt client id in {}, generating a new one", path.display());
+    }
+
+    let mut id = 0;
+    while id == 0 {
+        id = rand::random();
+    }
+
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, id.to_string())?;
+    Ok(id)
+}
+
+/// Reads the saved progress of a profile, `None` when it never saved anything.
+pub fn load_profile(profile: &str) -> Result<Option<SavedProfile>, Box<dyn Error>> {
+    let path = profile_dir(profile).join(PROFILE_FILE);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+
+    let header: ProfileHeader = ron::from_str(&contents)?;
+    if header.version > PROFILE_VERSION {
+        return Err(format!("{} was saved by a newer version ({} > {PROFILE_VERSION})", path.display(), header.version).into());
+    }
+
+    let mut saved = if header.version == PROFILE_VERSION {
+        ron::from_str(&contents)?
+    } else {
+        bevy::log::info!("Migrating {} from version {}", path.display(), header.version);
+        migrate(&contents, header.version)?
+    };
+    saved.version = PROFILE_VERSION;
+
+    Ok(Some(saved))
+}
+
+/// Brings an older save up to the current layout. Each old layout keeps its own struct here.
+fn migrate(contents: &str, version: u32) -> Result<SavedProfile, Box<dyn Error>> {
+    match version {
+        // Saves without a version only lack fields, which fall back to their defaults
+        0 => Ok(ron::from_str(contents)?),
+        _ => Err(format!("no migration from profile version {version}").into()),
+    }
+}
+
+pub fn save_profile(profile: &str, saved: &SavedProfile) -> Result<(), Box<dyn Error>> {
+    let dir = profile_dir(profile);
+    fs::create_dir_all(&dir)?;
+
+    let contents = ron::ser::to_string_pretty(saved, ron::ser::PrettyConfig::default())?;
+    write_atomically(&dir.join(PROFILE_FILE), &contents)?;
+    Ok(())
+}
+
+/// Writes next to the old file first, so a crash mid-save never loses what was there.
+pub fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    fs::write(&temp_path, contents)?;
+    fs::rename(temp_path, path)
+}
+
+/// Collects changes and lets them be written at most every few seconds, instead of on every frame something changed.
+pub struct SaveThrottle {
+    dirty: bool,
+    timer: Timer,
+}
+
+impl Default for SaveThrottle {
+    fn default() -> Self {
+        SaveThrottle { dirty: false, timer: Timer::from_seconds(SAVE_INTERVAL_SECONDS, TimerMode::Repeating) }
+    }
+}
+
+impl SaveThrottle {
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// True once the interval is up and something changed since the last save.
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        self.timer.tick(delta);
+        self.timer.just_finished() && self.take()
+    }
+
+    /// Whether anything changed since the last save, counting it as saved from now on.
+    pub fn take(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::components::character::{Character, LocalPlayer};
+use crate::components::player::{CharacterXp, ItemStats, LevelUp, PlayerData};
+use crate::plugins::network::OwnedBy;
+use crate::preludes::network_preludes::*;
+
+const XP_CURVE_FILE: &str = "xp.curve.ron";
+const LEVEL_UP_EFFECT_SECONDS: f32 = 1.2;
+
+/// Xp needed per level and what every level adds to a character, from `assets/xp.curve.ron`.
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct XpCurve {
+    /// Xp from level 1 to level 2
+    pub base: u64,
+    /// Every level takes this many times the xp of the one before
+    pub growth: f32,
+    pub max_level: u64,
+    pub health_per_level: i64,
+    /// Added to the damage multiplier per level
+    pub damage_per_level: f32,
+}
+
+impl Default for XpCurve {
+    fn default() -> Self {
+        XpCurve { base: 5, growth: 1.5, max_level: 30, health_per_level: 20, damage_per_level: 0.05 }
+    }
+}
+
+impl XpCurve {
+    pub fn xp_to_next(&self, level: u64) -> u64 {
+        let steps = level.clamp(1, self.max_level.max(1)) - 1;
+        (self.base as f64 * (self.growth as f64).powi(steps as i32)).round().max(1.0) as u64
+    }
+
+    /// Spends banked xp on levels, returns the new level and the xp left over.
+    pub fn level_up(&self, level: u64, mut xp: u64) -> (u64, u64) {
+        let mut level = level.max(1);
+        while level < self.max_level && xp >= self.xp_to_next(level) {
+            xp -= self.xp_to_next(level);
+            level += 1;
+        }
+        (level, xp)
+    }
+
+    /// Stat growth from every level past the first.
+    pub fn level_stats(&self, level: u64) -> ItemStats {
+        let gained = level.clamp(1, self.max_level.max(1)) - 1;
+        ItemStats {
+            max_health: self.health_per_level * gained as i64,
+            damage: self.damage_per_level * gained as f32,
+            ..default()
+        }
+    }
+}
+
+#[derive(Default)]
+struct XpCurveLoader;
+
+impl AssetLoader for XpCurveLoader {
+    type Asset = XpCurve;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<XpCurve, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["curve.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct XpCurveFile(Handle<XpCurve>);
+
+pub struct ProgressionPlugin;
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<XpCurve>()
+        .init_asset::<XpCurve>()
+        .init_asset_loader::<XpCurveLoader>()
+        .add_server_event::<LevelUp>(Channel::Ordered)
+        .add_systems(Startup, load_xp_curve)
+        .add_systems(PreUpdate, update_xp_curve)
+        .add_systems(Update, level_ups.run_if(server_running));
+    }
+}
+
+pub struct ProgressionPresentationPlugin;
+impl Plugin for ProgressionPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, (spawn_level_up_effect, animate_level_up_effect));
+    }
+}
+
+fn load_xp_curve(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(XpCurveFile(assets.load(XP_CURVE_FILE)));
+}
+
+fn update_xp_curve(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<XpCurve>>,
+    file: Res<XpCurveFile>,
+    curves: Res<Assets<XpCurve>>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&file.0) && !event.is_modified(&file.0) {
+            continue;
+        }
+        if let Some(curve) = curves.get(&file.0) {
+            commands.insert_resource(curve.clone());
+        }
+    }
+}
+
+fn level_ups(
+    curve: Res<XpCurve>,
+    mut players: Query<(&OwnedBy, &mut CharacterXp), (With<PlayerData>, Changed<CharacterXp>)>,
+    mut level_ups: EventWriter<ToClients<LevelUp>>,
+) {
+    for (owner, mut xp) in players.iter_mut() {
+        let (level, value) = curve.level_up(xp.level, xp.value);
+        if level == xp.level && value == xp.value {
+            continue;
+        }
+
+        let gained = level > xp.level;
+        xp.level = level;
+        xp.value = value;
+
+        if gained {
+            level_ups.write(ToClients { mode: SendMode::Direct(owner.0), event: LevelUp { level } });
+        }
+    }
+}
+
+#[derive(Component)]
+struct LevelUpEffect {
+    timer: Timer,
+}
+
+// A golden ring that rises around the character and fades out
+fn spawn_level_up_effect(
+    mut commands: Commands,
+    mut level_ups: EventReader<LevelUp>,
+    character: Query<Entity, (With<LocalPlayer>, With<Character>, With<Transform>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if level_ups.read().count() == 0 {
+        return;
+    }
+    let Ok(character) = character.single() else { return };
+
+    let ring = commands.spawn((
+        Mesh3d(meshes.add(Torus::new(0.6, 0.7))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 0.85, 0.2, 1.0),
+            emissive: LinearRgba::rgb(2.0, 1.6, 0.4),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })),
+        Transform::default(),
+        LevelUpEffect { timer: Timer::from_seconds(LEVEL_UP_EFFECT_SECONDS, TimerMode::Once) },
+    )).id();
+
+    commands.entity(character).add_child(ring);
+}
+
+fn animate_level_up_effect(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut effects: Query<(Entity, &mut Transform, &MeshMaterial3d<StandardMaterial>, &mut LevelUpEffect)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (entity, mut transform, material, mut effect) in effects.iter_mut() {
+        effect.timer.tick(time.delta());
+        if effect.timer.finished() {
+            materials.remove(&material.0);
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let progress = effect.timer.fraction();
+        transform.translation.y = progress * 1.5;
+        transform.scale = Vec3::splat(1.0 + progress * 0.5);
+        if let Some(material) = materials.get_mut(&material.0) {
+            material.base_color.set_alpha(1.0 - progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(growth: f32) -> XpCurve {
+        XpCurve { base: 5, growth, max_level: 10, health_per_level: 20, damage_per_level: 0.05 }
+    }
+
+    #[test]
+    fn xp_to_next_grows_per_level() {
+        let curve = curve(1.5);
+        assert_eq!(curve.xp_to_next(1), 5);
+        assert_eq!(curve.xp_to_next(2), 8);
+        assert_eq!(curve.xp_to_next(3), 11);
+        // Level 0 is treated as level 1, levels past the cap cost as much as the last one
+        assert_eq!(curve.xp_to_next(0), 5);
+        assert_eq!(curve.xp_to_next(50), curve.xp_to_next(10));
+    }
+
+    #[test]
+    fn one_gain_can_cover_several_levels() {
+        let curve = curve(1.5);
+        assert_eq!(curve.level_up(1, 4), (1, 4));
+        assert_eq!(curve.level_up(1, 5), (2, 0));
+        assert_eq!(curve.level_up(1, 5 + 8 + 11 + 2), (4, 2));
+    }
+
+    #[test]
+    fn levels_stop_at_the_max_level() {
+        let curve = curve(1.5);
+        let needed: u64 = (1..10).map(|level| curve.xp_to_next(level)).sum();
+
+        assert_eq!(curve.level_up(1, needed + 3), (10, 3));
+        assert_eq!(curve.level_up(10, 1000), (10, 1000));
+        assert_eq!(curve.level_up(12, 0), (12, 0));
+    }
+
+    #[test]
+    fn flat_and_shrinking_growth_still_cost_xp() {
+        assert_eq!(curve(1.0).xp_to_next(7), 5);
+        assert_eq!(curve(1.0).level_up(1, 12), (3, 2));
+
+        for growth in [0.5, 0.0, -1.0] {
+            let curve = curve(growth);
+            assert!((1..=10).all(|level| curve.xp_to_next(level) >= 1), "growth {growth} made a level free");
+            assert_eq!(curve.level_up(1, 1000).0, 10);
+        }
+    }
+
+    #[test]
+    fn large_xp_values_do_not_overflow() {
+        let curve = XpCurve { growth: 1000.0, max_level: 30, ..curve(1.5) };
+        assert_eq!(curve.xp_to_next(30), u64::MAX);
+
+        let (level, left) = curve.level_up(1, u64::MAX);
+        assert!(level < 30);
+        assert!(left < curve.xp_to_next(level));
+
+        let (level, left) = self::curve(1.5).level_up(1, u64::MAX);
+        assert_eq!(level, 10);
+        assert!(left > 0);
+    }
+
+    #[test]
+    fn level_stats_count_levels_past_the_first() {
+        let curve = curve(1.5);
+        assert_eq!(curve.level_stats(1), ItemStats::default());
+        assert_eq!(curve.level_stats(3).max_health, 40);
+        assert!((curve.level_stats(3).damage - 0.1).abs() < 1e-6);
+        assert_eq!(curve.level_stats(99).max_health, curve.level_stats(10).max_health);
+    }
+}
@@ -2,7 +2,8 @@ use bevy::prelude::*;
 
 use crate::components::island_maps::{IslandMaps, TileType};
 use crate::components::island::OnIsland;
-use crate::plugins::attack::DamageEvent;
+use crate::plugins::attack::{AttackId, DamageEvent};
+use crate::plugins::lag_compensation::HitTargets;
 
 pub struct ProjectilePlugin;
 impl Plugin for ProjectilePlugin {
@@ -12,6 +13,14 @@ impl Plugin for ProjectilePlugin {
     }
 }
 
+pub struct ProjectilePresentationPlugin;
+impl Plugin for ProjectilePresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(PreUpdate, visualize_projectile);
+    }
+}
+
 #[derive(Component)]
 pub struct Projectile {
     pub owner: Entity,
@@ -20,6 +29,24 @@ pub struct Projectile {
     pub range: u8,
     pub speed: f32,
     pub damage: u64,
+    pub attack_id: AttackId,
+}
+
+fn visualize_projectile(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>, 
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    projectiles: Query<Entity, Added<Projectile>>,
+) {
+    for entity in &projectiles {
+        commands.entity(entity).insert((
+            Mesh3d(meshes.add(Cuboid::new(0.3, 0.3, 0.3))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(1.0, 1.0, 1.0),
+                ..Default::default()
+            })),
+        ));
+    }
 }
 
 fn projectile_system(
@@ -27,6 +54,7 @@ fn projectile_system(
     mut commands: Commands,
     mut projectiles: Query<(Entity, &mut Transform, &mut Projectile, &OnIsland)>,
     island_maps: Res<IslandMaps>,
+    targets: HitTargets,
 ) {
     for (entity, mut transform, mut projectile, island) in &mut projectiles {
         let delta = projectile.speed * time.delta_secs();
@@ -36,25 +64,21 @@ fn projectile_system(
 
         if let Some(map) = island_maps.get_map(island.0) {
             let tile_pos = IVec3::new(transform.translation.x.round() as i32, transform.translation.y.round() as i32, transform.translation.z.round() as i32);
-            let tile = map.get_tile(tile_pos);
+            if let TileType::Terrain(_) = map.get_tile(tile_pos).kind {
+                commands.entity(entity).despawn();
+                continue;
+            }
 
-            match tile.kind {
-                TileType::Terrain(_) => {
-                    commands.entity(entity).despawn();
-                    continue;
-                },
-                TileType::Player | TileType::Enemy => {
-                    if tile.entity != projectile.owner {
-                        commands.trigger(DamageEvent::new(
-                            projectile.owner,
-                            island.0,
-                            tile_pos,
-                            projectile.damage
-                        ));
-                        commands.entity(entity).despawn();
-                    }
-                }
-                _ => (),
+            // Thrown by a client, the dagger flies through the world that client saw
+            if targets.target(projectile.owner, island.0, tile_pos).is_some_and(|target| target != projectile.owner) {
+                commands.trigger(DamageEvent::new(
+                    projectile.owner,
+                    island.0,
+                    tile_pos,
+                    projectile.damage,
+                    projectile.attack_id
+                ));
+                commands.entity(entity).despawn();
             }
 
             if projectile.traveled >= projectile.range as f32 {
@@ -1,24 +1,46 @@
 use bevy::prelude::*;
-use bevy_replicon::prelude::{AppRuleExt, Channel, ClientTriggerAppExt, ClientTriggerExt, FromClient, SendMode, ServerTriggerAppExt, ServerTriggerExt, ToClients};
+use bevy_replicon::prelude::{client_connected, AppRuleExt, RepliconClient, Channel, ClientTriggerAppExt, ClientTriggerExt, FromClient, SendMode, ServerTriggerAppExt, ServerTriggerExt, ToClients, SERVER};
 use crate::components::character::LocalPlayer;
 use crate::OverworldSet;
 use crate::components::overworld::*;
 use crate::plugins::camera::{DollyCamera, NewCameraTarget, PlayerCamera};
+use crate::plugins::network::{OwnedBy, ResumeShipPosition};
+use crate::plugins::overworld::OCEAN_BOUNDS;
+
+pub const MAX_SHIP_SPEED: f32 = 5.0;
+const SHIP_SPAWN: Vec3 = Vec3::new(0.0, 0.3, 0.75);
+const SHIP_ACCELERATION: f32 = 20.0;
+const SHIP_KEYFRAME_SECONDS: f32 = 0.5;
+const MAX_PENDING_INPUTS: usize = 256;
+const SNAP_DISTANCE: f32 = 2.0;
+const RECONCILE_BLEND: f32 = 0.3;
 
 pub struct ShipPlugin;
 impl Plugin for ShipPlugin {
     fn build(&self, app: &mut App) {
         app
         .replicate::<Ship>()
-        .add_client_trigger::<ClientShipPosition>(Channel::Unreliable)
+        .replicate::<ShipSpawnPosition>()
+        .add_client_trigger::<ClientShipInput>(Channel::Unreliable)
         .add_server_trigger::<ServerShipPosition>(Channel::Unreliable)
         .add_observer(server_ship_move_update)
         .add_observer(client_ship_move_update)
+        .add_systems(Update, (
+            spawn_overworld_ship,
+            (reset_ship_owner_input, server_integrate_ships, server_send_ship_positions).chain().after(spawn_overworld_ship).run_if(not(client_connected)),
+        ));
+    }
+}
+
+pub struct ShipPresentationPlugin;
+impl Plugin for ShipPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
         .add_systems(
             Update,
             (   (
                 user_ship_movement,
-                spawn_overworld_ship,
+                visualize_ship.after(spawn_overworld_ship),
                 ).in_set(OverworldSet),
             )
         );
@@ -27,26 +49,50 @@ impl Plugin for ShipPlugin {
 
 fn spawn_overworld_ship(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>, 
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut ships: Query<(Entity, Option<&LocalPlayer>), (With<Ship>, Without<Transform>)>,
+    ships: Query<(Entity, Option<&ShipSpawnPosition>, Has<LocalPlayer>), (With<Ship>, Without<Transform>)>,
     world_root_query: Query<Entity, With<OverworldRoot>>,
+    resume_position: Option<Res<ResumeShipPosition>>,
 ) {
     if let Ok(overworld_root) = world_root_query.single() {
-        for (entity, local) in ships.iter_mut() {
+        for (entity, spawn_position, local) in ships.iter() {
+            // The server keeps where each client's ship was and replicates it, the host reads its own profile
+            let saved = spawn_position.map(|position| position.0)
+                .or_else(|| resume_position.as_ref().filter(|_| local).map(|position| position.0));
+
             commands.entity(entity).insert((
-                Mesh3d(meshes.add(Cuboid::new(0.5, 0.5, 0.5))),
-                MeshMaterial3d(materials.add(StandardMaterial {
-                    base_color: Color::srgb(0.65, 0.45, 0.25),
-                    ..Default::default()
-                })),
-                Transform::from_xyz(0.0, 0.3, 0.75),
+                Transform::from_translation(saved.map_or(SHIP_SPAWN, ship_spawn_position)),
                 Visibility::Inherited
             )).insert(ChildOf(overworld_root));
-    
-            if local.is_some() { 
-                commands.entity(entity).insert(NewCameraTarget);
-            }
+        }
+    }
+}
+
+/// Keeps a saved position inside the ocean and on the water.
+pub fn ship_spawn_position(saved: Vec3) -> Vec3 {
+    Vec3::new(
+        saved.x.clamp(-OCEAN_BOUNDS, OCEAN_BOUNDS),
+        SHIP_SPAWN.y,
+        saved.z.clamp(-OCEAN_BOUNDS, OCEAN_BOUNDS),
+    )
+}
+
+fn visualize_ship(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>, 
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    ships: Query<(Entity, Option<&LocalPlayer>), (With<Ship>, With<Transform>, Without<Mesh3d>)>,
+) {
+    for (entity, local) in ships.iter() {
+        commands.entity(entity).insert((
+            Mesh3d(meshes.add(Cuboid::new(0.5, 0.5, 0.5))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(0.65, 0.45, 0.25),
+                ..Default::default()
+            })),
+        ));
+
+        if local.is_some() { 
+            commands.entity(entity).insert(NewCameraTarget);
         }
     }
 }
@@ -55,11 +101,11 @@ fn user_ship_movement(
     time: Res<Time>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     camera: Query<&DollyCamera, With<PlayerCamera>>,
-
-    mut ship: Query<(Entity, &mut Transform), (With<Ship>, With<LocalPlayer>)>,
+    mut ship: Query<(Entity, &mut Transform, &mut ShipMotion, &mut ShipPrediction), (With<Ship>, With<LocalPlayer>)>,
+    client: Res<RepliconClient>,
     mut commands: Commands
 ) {
-    if let Ok((entity, mut ship_transform)) = ship.single_mut() {
+    if let Ok((entity, mut ship_transform, mut motion, mut prediction)) = ship.single_mut() {
         let mut direction = Vec3::ZERO;
 
         if keyboard_input.pressed(KeyCode::KeyW) {
@@ -84,39 +130,161 @@ fn user_ship_movement(
                 _ => direction,
             };
         }
+        direction = direction.normalize_or_zero();
+
+        // Keep sending idle input until the server has applied it, inputs may get lost
+        if direction == Vec3::ZERO && motion.direction == Vec3::ZERO && prediction.pending.is_empty() {
+            return;
+        }
+
+        prediction.next_sequence += 1;
+        let sequence = prediction.next_sequence;
+
+        commands.client_trigger_targets(
+            ClientShipInput { direction, sequence },
+            entity
+        );
 
-        if direction != Vec3::ZERO {
-            let speed = 5.0;
-            ship_transform.translation += direction.normalize() * speed * time.delta_secs();
-            
-            commands.client_trigger_targets(
-                ClientShipPosition(ship_transform.translation),
-                entity
-            );
+        // The server integrates its own ship, a connected client predicts until the server answers
+        if client.is_connected() {
+            motion.direction = direction;
+            let (mut position, mut velocity) = (ship_transform.translation, motion.velocity);
+            integrate_ship(&mut position, &mut velocity, direction, time.delta_secs());
+            ship_transform.translation = position;
+            motion.velocity = velocity;
+
+            prediction.pending.push_back(ShipInputRecord { sequence, direction, delta: time.delta_secs() });
+            if prediction.pending.len() > MAX_PENDING_INPUTS {
+                prediction.pending.pop_front();
+            }
         }
     }
 }
 
+/// Moves a ship one step towards its input direction, shared by the server and client prediction.
+pub fn integrate_ship(position: &mut Vec3, velocity: &mut Vec3, direction: Vec3, delta: f32) {
+    let target = direction.clamp_length_max(1.0) * MAX_SHIP_SPEED;
+    *velocity = velocity.move_towards(target, SHIP_ACCELERATION * delta).clamp_length_max(MAX_SHIP_SPEED);
+
+    *position += *velocity * delta;
+    position.x = position.x.clamp(-OCEAN_BOUNDS, OCEAN_BOUNDS);
+    position.z = position.z.clamp(-OCEAN_BOUNDS, OCEAN_BOUNDS);
+
+    if position.x.abs() >= OCEAN_BOUNDS {
+        velocity.x = 0.0;
+    }
+    if position.z.abs() >= OCEAN_BOUNDS {
+        velocity.z = 0.0;
+    }
+}
+
 fn client_ship_move_update(
     trigger: Trigger<ServerShipPosition>,
-    mut ships: Query<&mut Transform, With<Ship>>,
+    mut ships: Query<(&mut Transform, &mut ShipMotion, &mut ShipPrediction, Option<&LocalPlayer>), With<Ship>>,
 ){
-    if let Ok(mut transform) = ships.get_mut(trigger.target()) {
+    let Ok((mut transform, mut motion, mut prediction, local)) = ships.get_mut(trigger.target()) else {
+        return;
+    };
+
+    if local.is_none() {
         transform.translation = trigger.position;
+        motion.velocity = trigger.velocity;
+        return;
     }
+
+    // Rewind to the server state and replay the inputs it has not seen yet
+    prediction.pending.retain(|input| input.sequence > trigger.last_input);
+
+    let mut position = trigger.position;
+    let mut velocity = trigger.velocity;
+    for input in prediction.pending.iter() {
+        integrate_ship(&mut position, &mut velocity, input.direction, input.delta);
+    }
+
+    let error = position.distance(transform.translation);
+    if error > SNAP_DISTANCE {
+        transform.translation = position;
+    } else {
+        transform.translation = transform.translation.lerp(position, RECONCILE_BLEND);
+    }
+    motion.velocity = velocity;
 }
 
 fn server_ship_move_update(
-    trigger: Trigger<FromClient<ClientShipPosition>>,
-    mut commands: Commands
+    trigger: Trigger<FromClient<ClientShipInput>>,
+    mut ships: Query<(&mut ShipMotion, &OwnedBy), With<Ship>>,
 ) {
-    commands.server_trigger_targets(ToClients {
-            mode: SendMode::BroadcastExcept(trigger.client_entity),
-            event: ServerShipPosition {
-                client_entity: trigger.client_entity,
-                position: trigger.0,
-            }
-        },
-        trigger.target()
-    ); 
-}
\ No newline at end of file
+    let Ok((mut motion, owner)) = ships.get_mut(trigger.target()) else {
+        return;
+    };
+
+    if owner.0 != trigger.client_entity {
+        warn!("{:?} sent input for a ship it does not own", trigger.client_entity);
+        return;
+    }
+
+    // Unreliable channel: late inputs are older than what we already applied
+    if trigger.sequence <= motion.last_input {
+        return;
+    }
+
+    if !trigger.direction.is_finite() {
+        return;
+    }
+
+    motion.direction = Vec3::new(trigger.direction.x, 0.0, trigger.direction.z).clamp_length_max(1.0);
+    motion.last_input = trigger.sequence;
+}
+
+// A resumed session starts counting its inputs from zero again
+fn reset_ship_owner_input(
+    mut ships: Query<&mut ShipMotion, (With<Ship>, Changed<OwnedBy>)>,
+) {
+    for mut motion in &mut ships {
+        motion.direction = Vec3::ZERO;
+        motion.last_input = 0;
+    }
+}
+
+fn server_integrate_ships(
+    time: Res<Time>,
+    mut ships: Query<(&mut Transform, &mut ShipMotion), With<Ship>>,
+) {
+    for (mut transform, mut motion) in &mut ships {
+        let (mut position, mut velocity) = (transform.translation, motion.velocity);
+        integrate_ship(&mut position, &mut velocity, motion.direction, time.delta_secs());
+        motion.velocity = velocity;
+
+        if position != transform.translation {
+            transform.translation = position;
+        }
+    }
+}
+
+// Ships are only visible to their owner, so that is the only one to keep up to date
+fn server_send_ship_positions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut keyframe: Local<Option<Timer>>,
+    ships: Query<(Entity, Ref<Transform>, &ShipMotion, &OwnedBy), With<Ship>>,
+) {
+    let keyframe = keyframe.get_or_insert_with(|| Timer::from_seconds(SHIP_KEYFRAME_SECONDS, TimerMode::Repeating));
+    let send_all = keyframe.tick(time.delta()).just_finished();
+
+    for (entity, transform, motion, owner) in &ships {
+        if owner.0 == SERVER || (!send_all && !transform.is_changed()) {
+            continue;
+        }
+
+        commands.server_trigger_targets(ToClients {
+                mode: SendMode::Direct(owner.0),
+                event: ServerShipPosition {
+                    position: transform.translation,
+                    velocity: motion.velocity,
+                    last_input: motion.last_input,
+                }
+            },
+            entity
+        ); 
+    }
+}
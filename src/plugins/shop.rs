@@ -0,0 +1,530 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use bevy_replicon::prelude::Replicated;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::components::character::Character;
+use crate::components::island::{FinishedSetupIsland, IslandRoot, OnIsland};
+use crate::components::island_maps::{IslandMaps, Tile, TileType};
+use crate::components::overworld::Island;
+use crate::components::player::{Gold, Inventory, ItemCatalogue, ItemId, ItemStack, PlayerData};
+use crate::plugins::network::OwnedBy;
+use crate::preludes::humanoid_preludes::*;
+use crate::preludes::network_preludes::*;
+use crate::IslandSet;
+
+const SHOP_FOLDER: &str = "shops";
+/// How many tiles away from a merchant a player can still trade.
+pub const MERCHANT_RANGE: i32 = 2;
+
+/// A merchant from `assets/shops`, prices come from the `ItemCatalogue`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Shop {
+    pub name: String,
+    /// Shown above the shop window
+    pub title: String,
+    /// Island the merchant stands on
+    pub island: u64,
+    /// Items the merchant sells, as many as the player can pay for
+    pub stock: Vec<ItemId>,
+    /// Share of an item's price the merchant pays when a player sells it
+    #[serde(default = "half")]
+    pub buyback: f32,
+}
+
+fn half() -> f32 {
+    0.5
+}
+
+impl Shop {
+    pub fn buy_price(&self, item: ItemId, catalogue: &ItemCatalogue) -> Option<u64> {
+        if !self.stock.contains(&item) {
+            return None;
+        }
+        catalogue.0.get(&item)?.price
+    }
+
+    /// Merchants take anything with a price, not only what they sell themselves.
+    pub fn sell_price(&self, item: ItemId, catalogue: &ItemCatalogue) -> Option<u64> {
+        let price = catalogue.0.get(&item)?.price?;
+        Some((price as f32 * self.buyback.clamp(0.0, 1.0)).floor() as u64)
+    }
+}
+
+/// One data file from `assets/shops`, `*.shop.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+#[serde(transparent)]
+pub struct ShopList(pub Vec<Shop>);
+
+#[derive(Default)]
+struct ShopListLoader;
+
+impl AssetLoader for ShopListLoader {
+    type Asset = ShopList;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<ShopList, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["shop.ron"]
+    }
+}
+
+/// Keeps the shop folder loaded, with the `dev` feature its files are watched for changes.
+#[derive(Resource)]
+struct ShopFiles {
+    _folder: Handle<LoadedFolder>,
+}
+
+/// Every shop by name.
+#[derive(Resource, Default)]
+pub struct Shops(pub HashMap<String, Shop>);
+
+/// A merchant standing on an island, replicated to the players there. Carries its own tile
+/// since positions are not replicated.
+#[derive(Component, Serialize, Deserialize, Debug)]
+#[require(Replicated)]
+pub struct Merchant {
+    pub shop: String,
+    pub position: IVec3,
+}
+
+impl Merchant {
+    pub fn in_range(&self, position: IVec3) -> bool {
+        (self.position - position).abs().max_element() <= MERCHANT_RANGE
+    }
+}
+
+/// Asks the server to buy items from a shop.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct BuyItem {
+    pub shop: String,
+    pub item: ItemId,
+    pub qty: u16,
+}
+
+/// Asks the server to sell items from an inventory slot to a shop.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct SellItem {
+    pub shop: String,
+    pub slot: usize,
+    pub item: ItemId,
+    pub qty: u16,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum TradeRejection {
+    UnknownShop,
+    TooFar,
+    NotInStock,
+    NotTradeable,
+    NotEnoughGold,
+    InventoryFull,
+    NoSuchItem,
+    /// The client has no player data to trade with
+    NoTrader,
+}
+
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct TradeRejected {
+    pub item: ItemId,
+    pub reason: TradeRejection,
+}
+
+pub struct ShopPlugin;
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<Shops>()
+        .init_asset::<ShopList>()
+        .init_asset_loader::<ShopListLoader>()
+        .replicate::<Merchant>()
+        .add_client_event::<BuyItem>(Channel::Ordered)
+        .add_client_event::<SellItem>(Channel::Ordered)
+        .add_server_event::<TradeRejected>(Channel::Ordered)
+        .add_systems(Startup, load_shop_files)
+        .add_systems(PreUpdate, update_shops)
+        .add_systems(Update, (
+            (spawn_merchants, buy_items, sell_items).chain().run_if(server_running),
+            trade_rejected,
+        ));
+    }
+}
+
+pub struct ShopPresentationPlugin;
+impl Plugin for ShopPresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_systems(Update, visualize_merchants.in_set(IslandSet));
+    }
+}
+
+fn load_shop_files(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(ShopFiles { _folder: assets.load_folder(SHOP_FOLDER) });
+}
+
+fn update_shops(
+    mut events: EventReader<AssetEvent<ShopList>>,
+    lists: Res<Assets<ShopList>>,
+    mut shops: ResMut<Shops>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    shops.0.clear();
+    for (_, list) in lists.iter() {
+        for shop in list.0.iter() {
+            if shops.0.insert(shop.name.clone(), shop.clone()).is_some() {
+                warn!("Shop '{}' is defined more than once", shop.name);
+            }
+        }
+    }
+
+    info!("Loaded {} shops", shops.0.len());
+}
+
+// Merchants stand on the free tile closest to the harbour, they leave with the island's enemies when it empties
+fn spawn_merchants(
+    mut commands: Commands,
+    shops: Res<Shops>,
+    islands: Query<&Island, With<FinishedSetupIsland>>,
+    merchants: Query<&Merchant, Without<RemoveEntity>>,
+    mut island_maps: ResMut<IslandMaps>,
+) {
+    for shop in shops.0.values() {
+        if !islands.iter().any(|island| island.0 == shop.island) || merchants.iter().any(|merchant| merchant.shop == shop.name) {
+            continue;
+        }
+        let Some(map) = island_maps.get_map_mut(shop.island) else { continue };
+
+        let harbour = map.leave_position;
+        let Some(position) = map.above_water_top_tiles().into_iter()
+            .map(|tile| tile + IVec3::Y)
+            .filter(|position| (*position - harbour).abs().max_element() >= 2 && map.can_move(*position))
+            .min_by_key(|position| (*position - harbour).length_squared())
+        else {
+            warn!("No room for merchant '{}' on island {}", shop.name, shop.island);
+            continue;
+        };
+
+        let merchant = commands.spawn((
+            Merchant { shop: shop.name.clone(), position },
+            Position::new(position),
+            OnIsland(shop.island),
+        )).id();
+
+        map.add_entity_ivec3(position, Tile::new(TileType::Enemy, merchant));
+    }
+}
+
+type Traders<'w, 's> = Query<'w, 's, (&'static OwnedBy, &'static mut Gold, &'static mut Inventory), With<PlayerData>>;
+
+/// The shop a client asked for, as long as its character stands next to that shop's merchant.
+fn shop_in_reach<'a>(
+    client: Entity,
+    name: &str,
+    shops: &'a Shops,
+    merchants: &Query<(&Merchant, &OnIsland)>,
+    characters: &Query<(&OwnedBy, &Position, &OnIsland), With<Character>>,
+) -> Result<&'a Shop, TradeRejection> {
+    let shop = shops.0.get(name).ok_or(TradeRejection::UnknownShop)?;
+    let (merchant, island) = merchants.iter().find(|(merchant, _)| merchant.shop == name).ok_or(TradeRejection::TooFar)?;
+
+    let in_reach = characters.iter().any(|(owner, position, on_island)| {
+        owner.0 == client && on_island.0 == island.0 && merchant.in_range(position.0)
+    });
+    if !in_reach {
+        return Err(TradeRejection::TooFar);
+    }
+    Ok(shop)
+}
+
+fn buy_items(
+    mut buy_events: EventReader<FromClient<BuyItem>>,
+    mut rejections: EventWriter<ToClients<TradeRejected>>,
+    shops: Res<Shops>,
+    catalogue: Res<ItemCatalogue>,
+    merchants: Query<(&Merchant, &OnIsland)>,
+    characters: Query<(&OwnedBy, &Position, &OnIsland), With<Character>>,
+    mut traders: Traders,
+) {
+    for FromClient { client_entity, event } in buy_events.read() {
+        let result = shop_in_reach(*client_entity, &event.shop, &shops, &merchants, &characters)
+            .and_then(|shop| buy(*client_entity, shop, event, &catalogue, &mut traders));
+
+        if let Err(reason) = result {
+            debug!("Rejected purchase of item {} from {:?}: {:?}", event.item, client_entity, reason);
+            rejections.write(ToClients {
+                mode: SendMode::Direct(*client_entity),
+                event: TradeRejected { item: event.item, reason },
+            });
+        }
+    }
+}
+
+fn buy(
+    client_entity: Entity,
+    shop: &Shop,
+    event: &BuyItem,
+    catalogue: &ItemCatalogue,
+    traders: &mut Traders,
+) -> Result<(), TradeRejection> {
+    let Some((_, mut gold, mut inventory)) = traders.iter_mut().find(|(owner, ..)| owner.0 == client_entity) else {
+        return Err(TradeRejection::NoTrader);
+    };
+
+    if event.qty == 0 {
+        return Err(TradeRejection::NoSuchItem);
+    }
+    if !shop.stock.contains(&event.item) {
+        return Err(TradeRejection::NotInStock);
+    }
+    let price = shop.buy_price(event.item, catalogue).ok_or(TradeRejection::NotTradeable)?;
+    let cost = price as u128 * event.qty as u128;
+    if gold.value < cost {
+        return Err(TradeRejection::NotEnoughGold);
+    }
+
+    let stack = ItemStack { id: event.item, qty: event.qty };
+    if !inventory.fits(&stack, catalogue) {
+        return Err(TradeRejection::InventoryFull);
+    }
+
+    gold.value -= cost;
+    inventory.add(stack, catalogue);
+    Ok(())
+}
+
+fn sell_items(
+    mut sell_events: EventReader<FromClient<SellItem>>,
+    mut rejections: EventWriter<ToClients<TradeRejected>>,
+    shops: Res<Shops>,
+    catalogue: Res<ItemCatalogue>,
+    merchants: Query<(&Merchant, &OnIsland)>,
+    characters: Query<(&OwnedBy, &Position, &OnIsland), With<Character>>,
+    mut traders: Traders,
+) {
+    for FromClient { client_entity, event } in sell_events.read() {
+        let result = shop_in_reach(*client_entity, &event.shop, &shops, &merchants, &characters)
+            .and_then(|shop| sell(*client_entity, shop, event, &catalogue, &mut traders));
+
+        if let Err(reason) = result {
+            debug!("Rejected sale of item {} from {:?}: {:?}", event.item, client_entity, reason);
+            rejections.write(ToClients {
+                mode: SendMode::Direct(*client_entity),
+                event: TradeRejected { item: event.item, reason },
+            });
+        }
+    }
+}
+
+fn sell(
+    client_entity: Entity,
+    shop: &Shop,
+    event: &SellItem,
+    catalogue: &ItemCatalogue,
+    traders: &mut Traders,
+) -> Result<(), TradeRejection> {
+    let Some((_, mut gold, mut inventory)) = traders.iter_mut().find(|(owner, ..)| owner.0 == client_entity) else {
+        return Err(TradeRejection::NoTrader);
+    };
+
+    // The slot is checked against the item too, the stack may have moved since the click
+    let in_slot = inventory.slots.get(event.slot)
+        .and_then(|slot| slot.as_ref())
+        .is_some_and(|stack| stack.id == event.item && stack.qty >= event.qty && event.qty > 0);
+    if !in_slot {
+        return Err(TradeRejection::NoSuchItem);
+    }
+
+    let price = shop.sell_price(event.item, catalogue).ok_or(TradeRejection::NotTradeable)?;
+    let sold = inventory.take_from_slot(event.slot, event.qty).map_err(|_| TradeRejection::NoSuchItem)?;
+    gold.value += price as u128 * sold.qty as u128;
+    Ok(())
+}
+
+fn trade_rejected(
+    mut rejections: EventReader<TradeRejected>,
+) {
+    for rejection in rejections.read() {
+        warn!("Trade of item {} refused: {:?}", rejection.item, rejection.reason);
+    }
+}
+
+fn visualize_merchants(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    merchants: Query<(Entity, &Merchant), Without<Mesh3d>>,
+    islandroot_query: Query<Entity, With<IslandRoot>>
+) {
+    let Ok(island_root) = islandroot_query.single() else {
+        return;
+    };
+
+    for (entity, merchant) in merchants.iter() {
+        commands.entity(entity).insert((
+            Mesh3d(meshes.add(Capsule3d::new(0.35, 0.6))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(0.55, 0.3, 0.7),
+                ..Default::default()
+            })),
+            Transform::from_translation(merchant.position.as_vec3()),
+        )).insert(ChildOf(island_root));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use crate::components::player::{ItemSpec, Rarity};
+
+    const POTION: ItemId = 1;
+    const SWORD: ItemId = 2;
+    const RELIC: ItemId = 3;
+
+    fn catalogue() -> ItemCatalogue {
+        let spec = |id, max, price| ItemSpec {
+            id,
+            name: id.to_string(),
+            description: String::new(),
+            max,
+            rarity: Rarity::Common,
+            price,
+            icon: None,
+            effect: None,
+        };
+
+        ItemCatalogue(HashMap::from([
+            (POTION, spec(POTION, 10, Some(5))),
+            (SWORD, spec(SWORD, 1, Some(40))),
+            (RELIC, spec(RELIC, 1, None)),
+        ]))
+    }
+
+    fn shop(buyback: f32) -> Shop {
+        Shop { name: "test".to_string(), title: String::new(), island: 0, stock: vec![POTION, SWORD, RELIC], buyback }
+    }
+
+    fn stack(id: ItemId, qty: u16) -> Option<ItemStack> {
+        Some(ItemStack { id, qty })
+    }
+
+    /// Runs a trade for a player with `gold` and `slots`, returns the outcome and what the player has afterwards.
+    fn trade(
+        gold: u128,
+        slots: Vec<Option<ItemStack>>,
+        trade: impl Fn(Entity, &mut Traders) -> Result<(), TradeRejection> + Send + Sync + 'static,
+    ) -> (Result<(), TradeRejection>, u128, Vec<Option<ItemStack>>) {
+        let mut world = World::new();
+        let client = world.spawn_empty().id();
+        let data = world.spawn((PlayerData, OwnedBy(client), Gold { value: gold }, Inventory { slots })).id();
+
+        let result = world.run_system_once(move |mut traders: Traders| trade(client, &mut traders)).unwrap();
+        let gold = world.get::<Gold>(data).unwrap().value;
+        let slots = world.get::<Inventory>(data).unwrap().slots.clone();
+        (result, gold, slots)
+    }
+
+    fn buying(item: ItemId, qty: u16) -> impl Fn(Entity, &mut Traders) -> Result<(), TradeRejection> + Send + Sync + 'static {
+        let (shop, catalogue) = (shop(0.5), catalogue());
+        let event = BuyItem { shop: shop.name.clone(), item, qty };
+        move |client: Entity, traders: &mut Traders| buy(client, &shop, &event, &catalogue, traders)
+    }
+
+    fn selling(slot: usize, item: ItemId, qty: u16) -> impl Fn(Entity, &mut Traders) -> Result<(), TradeRejection> + Send + Sync + 'static {
+        let (shop, catalogue) = (shop(0.5), catalogue());
+        let event = SellItem { shop: shop.name.clone(), slot, item, qty };
+        move |client: Entity, traders: &mut Traders| sell(client, &shop, &event, &catalogue, traders)
+    }
+
+    #[test]
+    fn buying_takes_gold_and_adds_the_items() {
+        assert_eq!(trade(100, vec![None, None], buying(POTION, 3)), (Ok(()), 85, vec![stack(POTION, 3), None]));
+    }
+
+    #[test]
+    fn buying_needs_enough_gold() {
+        assert_eq!(trade(9, vec![None], buying(POTION, 2)), (Err(TradeRejection::NotEnoughGold), 9, vec![None]));
+    }
+
+    #[test]
+    fn buying_needs_room_for_all_of_it() {
+        let slots = vec![stack(SWORD, 1), stack(POTION, 8)];
+        assert_eq!(trade(100, slots.clone(), buying(POTION, 3)), (Err(TradeRejection::InventoryFull), 100, slots.clone()));
+        assert_eq!(trade(100, slots.clone(), buying(POTION, 2)), (Ok(()), 90, vec![stack(SWORD, 1), stack(POTION, 10)]));
+    }
+
+    #[test]
+    fn buying_rejects_nothing_and_items_not_for_sale() {
+        assert_eq!(trade(100, vec![None], buying(POTION, 0)), (Err(TradeRejection::NoSuchItem), 100, vec![None]));
+        assert_eq!(trade(100, vec![None], buying(99, 1)), (Err(TradeRejection::NotInStock), 100, vec![None]));
+        assert_eq!(trade(100, vec![None], buying(RELIC, 1)), (Err(TradeRejection::NotTradeable), 100, vec![None]));
+    }
+
+    #[test]
+    fn trading_needs_player_data() {
+        let stranger = Entity::from_raw(999);
+        let buying = move |_: Entity, traders: &mut Traders| {
+            let event = BuyItem { shop: "test".to_string(), item: POTION, qty: 1 };
+            buy(stranger, &shop(0.5), &event, &catalogue(), traders)
+        };
+        let selling = move |_: Entity, traders: &mut Traders| {
+            let event = SellItem { shop: "test".to_string(), slot: 0, item: POTION, qty: 1 };
+            sell(stranger, &shop(0.5), &event, &catalogue(), traders)
+        };
+
+        assert_eq!(trade(100, vec![None], buying), (Err(TradeRejection::NoTrader), 100, vec![None]));
+        assert_eq!(trade(100, vec![stack(POTION, 1)], selling), (Err(TradeRejection::NoTrader), 100, vec![stack(POTION, 1)]));
+    }
+
+    #[test]
+    fn selling_pays_the_buyback_price() {
+        let slots = vec![stack(POTION, 5)];
+        assert_eq!(trade(0, slots.clone(), selling(0, POTION, 3)), (Ok(()), 6, vec![stack(POTION, 2)]));
+        assert_eq!(trade(0, slots, selling(0, POTION, 5)), (Ok(()), 10, vec![None]));
+    }
+
+    #[test]
+    fn selling_checks_the_slot_holds_the_item() {
+        let slots = vec![stack(SWORD, 1), stack(POTION, 2), stack(RELIC, 1)];
+        for (slot, item, qty, reason) in [
+            (0, POTION, 1, TradeRejection::NoSuchItem),
+            (1, POTION, 3, TradeRejection::NoSuchItem),
+            (1, POTION, 0, TradeRejection::NoSuchItem),
+            (5, POTION, 1, TradeRejection::NoSuchItem),
+            (2, RELIC, 1, TradeRejection::NotTradeable),
+        ] {
+            assert_eq!(trade(7, slots.clone(), selling(slot, item, qty)), (Err(reason), 7, slots.clone()));
+        }
+    }
+
+    #[test]
+    fn buyback_rounds_down_per_item() {
+        let catalogue = catalogue();
+        assert_eq!(shop(0.5).sell_price(POTION, &catalogue), Some(2));
+        assert_eq!(shop(0.5).sell_price(SWORD, &catalogue), Some(20));
+        assert_eq!(shop(0.99).sell_price(POTION, &catalogue), Some(4));
+        assert_eq!(shop(1.5).sell_price(POTION, &catalogue), Some(5));
+        assert_eq!(shop(-1.0).sell_price(POTION, &catalogue), Some(0));
+        assert_eq!(shop(0.5).sell_price(RELIC, &catalogue), None);
+    }
+}
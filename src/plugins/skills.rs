@@ -0,0 +1,359 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::attacks::base_attack::BaseAttack;
+use crate::components::character::{Character, Loadout};
+use crate::components::player::{CharacterXp, EquipSkill, PlayerData, SkillLoadout, SkillRejected, SkillRejection, UnlockSkill, UnlockedSkills, SKILL_SLOTS};
+use crate::plugins::attack::{key_of, AttackId, AttackRegistry};
+use crate::plugins::network::OwnedBy;
+use crate::preludes::network_preludes::*;
+
+const SKILL_TREE_FILE: &str = "skills.tree.ron";
+
+/// The skill tree as written in `assets/skills.tree.ron`.
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct SkillTreeFile {
+    pub points_per_level: u32,
+    pub skills: Vec<SkillNodeFile>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SkillNodeFile {
+    /// Attack name as registered in the `AttackRegistry`
+    pub attack: String,
+    pub cost: u32,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    #[serde(default = "first_level")]
+    pub min_level: u64,
+}
+
+fn first_level() -> u64 {
+    1
+}
+
+#[derive(Default)]
+struct SkillTreeLoader;
+
+impl AssetLoader for SkillTreeLoader {
+    type Asset = SkillTreeFile;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<SkillTreeFile, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tree.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct SkillTreeHandle(Handle<SkillTreeFile>);
+
+#[derive(Clone, Debug)]
+pub struct SkillNode {
+    pub attack: AttackId,
+    pub name: String,
+    pub cost: u32,
+    pub requires: Vec<AttackId>,
+    pub min_level: u64,
+}
+
+impl SkillNode {
+    /// Free skills without requirements, every player knows them.
+    pub fn is_starter(&self) -> bool {
+        self.cost == 0 && self.requires.is_empty() && self.min_level <= 1
+    }
+}
+
+/// The skill tree with attack names resolved to ids.
+#[derive(Resource, Default, Debug)]
+pub struct SkillTree {
+    pub points_per_level: u32,
+    pub nodes: Vec<SkillNode>,
+}
+
+impl SkillTree {
+    pub fn node(&self, attack: AttackId) -> Option<&SkillNode> {
+        self.nodes.iter().find(|node| node.attack == attack)
+    }
+
+    pub fn name_of(&self, attack: AttackId) -> Option<&str> {
+        self.node(attack).map(|node| node.name.as_str())
+    }
+
+    pub fn points_available(&self, xp: &CharacterXp, unlocked: &UnlockedSkills) -> u32 {
+        let earned = self.points_per_level.saturating_mul(xp.level.saturating_sub(1) as u32);
+        earned.saturating_sub(unlocked.points_spent)
+    }
+
+    /// Checks whether a skill can be unlocked, returns its cost.
+    pub fn can_unlock(&self, attack: AttackId, xp: &CharacterXp, unlocked: &UnlockedSkills) -> Result<u32, SkillRejection> {
+        let node = self.node(attack).ok_or(SkillRejection::NotInTree)?;
+        if unlocked.skills.contains(&attack) {
+            return Err(SkillRejection::AlreadyUnlocked);
+        }
+        if !node.requires.iter().all(|required| unlocked.skills.contains(required)) {
+            return Err(SkillRejection::MissingRequirement);
+        }
+        if xp.level < node.min_level {
+            return Err(SkillRejection::LevelTooLow);
+        }
+        if self.points_available(xp, unlocked) < node.cost {
+            return Err(SkillRejection::NotEnoughPoints);
+        }
+        Ok(node.cost)
+    }
+}
+
+pub struct SkillsPlugin;
+impl Plugin for SkillsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<SkillTree>()
+        .init_asset::<SkillTreeFile>()
+        .init_asset_loader::<SkillTreeLoader>()
+        .replicate::<UnlockedSkills>()
+        .replicate::<SkillLoadout>()
+        .add_client_event::<UnlockSkill>(Channel::Ordered)
+        .add_client_event::<EquipSkill>(Channel::Ordered)
+        .add_server_event::<SkillRejected>(Channel::Ordered)
+        .add_systems(Startup, load_skill_tree)
+        .add_systems(PreUpdate, update_skill_tree)
+        .add_systems(Update, (
+            (grant_starter_skills, unlock_skills, equip_skills, sync_character_loadouts).chain().run_if(server_running),
+            skill_rejected,
+        ));
+    }
+}
+
+fn load_skill_tree(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+) {
+    commands.insert_resource(SkillTreeHandle(assets.load(SKILL_TREE_FILE)));
+}
+
+// Attacks register themselves on startup, scripted attacks once the attack file has loaded, so the tree is resolved again when the registry changes
+fn update_skill_tree(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<SkillTreeFile>>,
+    handle: Res<SkillTreeHandle>,
+    files: Res<Assets<SkillTreeFile>>,
+    registry: Res<AttackRegistry>,
+) {
+    let file_changed = events.read().filter(|event| event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0)).count() > 0;
+    if !file_changed && !registry.is_changed() {
+        return;
+    }
+    let Some(file) = files.get(&handle.0) else { return };
+
+    let resolve = |name: &str| {
+        let id = registry.id_of(name);
+        if id.is_none() {
+            warn!("Skill tree refers to unknown attack '{name}'");
+        }
+        id
+    };
+
+    let nodes: Vec<SkillNode> = file.skills.iter()
+        .filter_map(|skill| Some(SkillNode {
+            attack: resolve(&skill.attack)?,
+            name: skill.attack.clone(),
+            cost: skill.cost,
+            requires: skill.requires.iter().map(|name| resolve(name)).collect::<Option<_>>()?,
+            min_level: skill.min_level,
+        }))
+        .collect();
+
+    info!("Loaded skill tree with {} skills", nodes.len());
+    commands.insert_resource(SkillTree { points_per_level: file.points_per_level, nodes });
+}
+
+fn grant_starter_skills(
+    tree: Res<SkillTree>,
+    mut players: Query<(Ref<PlayerData>, &mut UnlockedSkills, &mut SkillLoadout)>,
+) {
+    for (data, mut unlocked, mut loadout) in players.iter_mut() {
+        if !tree.is_changed() && !data.is_added() {
+            continue;
+        }
+
+        for node in tree.nodes.iter().filter(|node| node.is_starter()) {
+            if !unlocked.skills.contains(&node.attack) {
+                unlocked.skills.push(node.attack);
+                loadout.add(node.attack);
+            }
+        }
+    }
+}
+
+fn unlock_skills(
+    mut unlock_events: EventReader<FromClient<UnlockSkill>>,
+    mut rejections: EventWriter<ToClients<SkillRejected>>,
+    tree: Res<SkillTree>,
+    mut players: Query<(&OwnedBy, &CharacterXp, &mut UnlockedSkills, &mut SkillLoadout), With<PlayerData>>,
+) {
+    for FromClient { client_entity, event } in unlock_events.read() {
+        let Some((_, xp, mut unlocked, mut loadout)) = players.iter_mut().find(|(owner, ..)| owner.0 == *client_entity) else {
+            continue;
+        };
+
+        match tree.can_unlock(event.attack, xp, &unlocked) {
+            Ok(cost) => {
+                unlocked.skills.push(event.attack);
+                unlocked.points_spent += cost;
+                loadout.add(event.attack);
+            }
+            Err(reason) => {
+                debug!("Rejected unlock of skill {} from {:?}: {:?}", event.attack, client_entity, reason);
+                rejections.write(ToClients {
+                    mode: SendMode::Direct(*client_entity),
+                    event: SkillRejected { attack: Some(event.attack), reason },
+                });
+            }
+        }
+    }
+}
+
+fn equip_skills(
+    mut equip_events: EventReader<FromClient<EquipSkill>>,
+    mut rejections: EventWriter<ToClients<SkillRejected>>,
+    mut players: Query<(&OwnedBy, &UnlockedSkills, &mut SkillLoadout), With<PlayerData>>,
+) {
+    for FromClient { client_entity, event } in equip_events.read() {
+        let Some((_, unlocked, mut loadout)) = players.iter_mut().find(|(owner, ..)| owner.0 == *client_entity) else {
+            continue;
+        };
+
+        let reason = if event.slot >= SKILL_SLOTS {
+            SkillRejection::InvalidSlot
+        } else if event.attack.is_some_and(|attack| !unlocked.skills.contains(&attack)) {
+            SkillRejection::NotUnlocked
+        } else {
+            loadout.set(event.slot, event.attack);
+            continue;
+        };
+
+        rejections.write(ToClients {
+            mode: SendMode::Direct(*client_entity),
+            event: SkillRejected { attack: event.attack, reason },
+        });
+    }
+}
+
+// The server only lets a character cast what its owner has equipped
+fn sync_character_loadouts(
+    mut characters: Query<(Ref<Character>, &OwnedBy, &mut Loadout)>,
+    players: Query<(&OwnedBy, Ref<SkillLoadout>), With<PlayerData>>,
+) {
+    for (character, owner, mut loadout) in characters.iter_mut() {
+        let Some((_, skills)) = players.iter().find(|(data_owner, _)| data_owner.0 == owner.0) else {
+            continue;
+        };
+        if !character.is_added() && !skills.is_changed() {
+            continue;
+        }
+
+        loadout.0 = std::iter::once(key_of::<BaseAttack>())
+            .chain(skills.0.iter().flatten().copied())
+            .collect();
+    }
+}
+
+fn skill_rejected(
+    mut rejections: EventReader<SkillRejected>,
+) {
+    for rejection in rejections.read() {
+        warn!("Skill change refused: {:?}", rejection.reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUT: AttackId = 1;
+    const DAGGER: AttackId = 2;
+    const COUNTER: AttackId = 3;
+
+    fn node(attack: AttackId, cost: u32, requires: Vec<AttackId>, min_level: u64) -> SkillNode {
+        SkillNode { attack, name: attack.to_string(), cost, requires, min_level }
+    }
+
+    fn tree() -> SkillTree {
+        SkillTree {
+            points_per_level: 1,
+            nodes: vec![
+                node(CUT, 0, vec![], 1),
+                node(DAGGER, 1, vec![CUT], 2),
+                node(COUNTER, 2, vec![CUT], 4),
+            ],
+        }
+    }
+
+    fn level(level: u64) -> CharacterXp {
+        CharacterXp { value: 0, level }
+    }
+
+    fn unlocked(skills: Vec<AttackId>, points_spent: u32) -> UnlockedSkills {
+        UnlockedSkills { skills, points_spent }
+    }
+
+    #[test]
+    fn points_come_from_levels_past_the_first() {
+        let tree = tree();
+        assert_eq!(tree.points_available(&level(1), &unlocked(vec![], 0)), 0);
+        assert_eq!(tree.points_available(&level(5), &unlocked(vec![], 0)), 4);
+        assert_eq!(tree.points_available(&level(5), &unlocked(vec![CUT, DAGGER], 1)), 3);
+        // Saves from a tree that paid out more never go below zero
+        assert_eq!(tree.points_available(&level(2), &unlocked(vec![], 3)), 0);
+        assert_eq!(SkillTree { points_per_level: 2, ..tree }.points_available(&level(3), &unlocked(vec![], 1)), 3);
+    }
+
+    #[test]
+    fn unlocking_returns_the_cost() {
+        let tree = tree();
+        assert_eq!(tree.can_unlock(DAGGER, &level(2), &unlocked(vec![CUT], 0)), Ok(1));
+        assert_eq!(tree.can_unlock(COUNTER, &level(4), &unlocked(vec![CUT], 0)), Ok(2));
+    }
+
+    #[test]
+    fn rejections_come_in_order() {
+        let tree = tree();
+        // Every check below also fails the ones after it, the first one decides the reason
+        assert_eq!(tree.can_unlock(99, &level(1), &unlocked(vec![], 0)), Err(SkillRejection::NotInTree));
+        assert_eq!(tree.can_unlock(DAGGER, &level(1), &unlocked(vec![DAGGER], 0)), Err(SkillRejection::AlreadyUnlocked));
+        assert_eq!(tree.can_unlock(DAGGER, &level(1), &unlocked(vec![], 5)), Err(SkillRejection::MissingRequirement));
+        assert_eq!(tree.can_unlock(COUNTER, &level(3), &unlocked(vec![CUT], 5)), Err(SkillRejection::LevelTooLow));
+        assert_eq!(tree.can_unlock(COUNTER, &level(4), &unlocked(vec![CUT], 2)), Err(SkillRejection::NotEnoughPoints));
+    }
+
+    #[test]
+    fn skills_learned_from_items_cost_no_points() {
+        let tree = tree();
+        let learned = unlocked(vec![CUT, DAGGER], 0);
+        assert_eq!(tree.points_available(&level(3), &learned), 2);
+        assert_eq!(tree.can_unlock(COUNTER, &level(4), &learned), Ok(2));
+    }
+
+    #[test]
+    fn only_free_skills_without_requirements_are_starters() {
+        let tree = tree();
+        assert!(tree.node(CUT).unwrap().is_starter());
+        assert!(!tree.node(DAGGER).unwrap().is_starter());
+        assert!(!node(4, 0, vec![], 2).is_starter());
+    }
+}
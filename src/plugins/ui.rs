@@ -1,19 +1,26 @@
 use bevy::prelude::*;
-use crate::{attacks::{base_attack::BaseAttack, counter::Counter, cut_through::CutThrough, dagger_throw::DaggerThrow}, components::{character::LocalPlayer, humanoid::{AttackCooldowns, Health}, player::{CharacterXp, Gold, Inventory}, ui::*}, plugins::attack::{key_of}};
+use crate::{components::{character::{Character, LocalPlayer}, humanoid::{AttackCooldowns, Health, Position}, player::{CharacterXp, EquipSkill, Equipment, EquipmentSlot, Gold, Inventory, ItemCatalogue, ItemId, LevelUp, PlayerData, SkillLoadout, Stash, UnequipItem, UnlockSkill, UnlockedSkills, UseItem, SKILL_SLOTS}, ui::*}, plugins::{network::ConnectionError, progression::XpCurve, shop::{BuyItem, Merchant, SellItem, Shops}, skills::SkillTree}};
 
 const BORDER_RADIUS : Val = Val::Px(5.0);
 const XP_BAR_WIDTH : f32 = 100.0;
 const BASE_FONT_SIZE : f32 = 18.0;
-pub const NUM_SKILLS: usize = 4;
+const TOAST_SECONDS : f32 = 2.5;
 pub const SKILL_ICON_SIZE: f32 = 48.0;
+/// Quick use keys for the first inventory slots, next to the skill keys
+const ITEM_HOTKEYS: [KeyCode; 4] = [KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8];
 
 pub struct UIPlugin;
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app
         .insert_resource(InventoryUIState::default())
+        .insert_resource(SkillTreeUIState::default())
+        .insert_resource(ShopUIState::default())
         .add_systems(Startup, setup_ui)
-        .add_systems(Update, (inventory_controls, xp_changed, character_health_changed, gold_changed, inventory_update, update_skill_cooldowns));
+        .add_systems(Update, (inventory_controls, use_item_controls, unequip_controls, xp_changed, level_up_toast, fade_toasts, character_health_changed, gold_changed, inventory_update, update_skill_cooldowns))
+        .add_systems(Update, (skill_tree_controls, skill_tree_update, skill_tree_buttons, skill_slots_changed))
+        .add_systems(Update, (shop_controls, shop_update, shop_buttons).chain())
+        .add_systems(Update, show_connection_error.run_if(resource_added::<ConnectionError>));
     }
 }
 
@@ -161,14 +168,7 @@ fn setup_ui(
             BackgroundColor(Color::NONE),
         ))
         .with_children(|parent| {
-            let skill_ids = vec![
-                key_of::<Counter>(),
-                key_of::<CutThrough>(),
-                key_of::<DaggerThrow>(),
-                key_of::<BaseAttack>(),
-            ];
-
-            for (index, attack_id) in skill_ids.iter().enumerate() {
+            for index in 0..SKILL_SLOTS {
                 parent.spawn((
                     Node {
                         width: Val::Px(SKILL_ICON_SIZE),
@@ -182,10 +182,27 @@ fn setup_ui(
                     BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 1.0)),
                     SkillSlot {
                         index,
-                        attack_id: *attack_id,
+                        attack_id: None,
                     },
                 ))
                 .with_children(|icon| {
+                    // Skill name (top)
+                    icon.spawn((
+                        Text::new(""),
+                        TextColor(Color::WHITE),
+                        TextFont {
+                            font_size: BASE_FONT_SIZE * 0.5,
+                            ..default()
+                        },
+                        SkillNameText,
+                        Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(2.0),
+                            top: Val::Px(2.0),
+                            ..default()
+                        },
+                    ));
+
                     // Cooldown text (centered)
                     icon.spawn((
                         Text::new(""),
@@ -246,6 +263,42 @@ fn setup_ui(
         BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 1.0)),
         InventoryPanel,
     ));
+
+    // Skill tree UI
+    commands.spawn((
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            right: Val::Px(50.0),
+            width: Val::Px(320.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..Default::default()
+        },
+        BorderRadius::all(BORDER_RADIUS),
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 1.0)),
+        SkillTreePanel,
+    ));
+
+    // Shop UI
+    commands.spawn((
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            left: Val::Percent(35.0),
+            width: Val::Px(360.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..Default::default()
+        },
+        BorderRadius::all(BORDER_RADIUS),
+        BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 1.0)),
+        ShopPanel,
+    ));
 }
 
 fn update_skill_cooldowns(
@@ -257,7 +310,7 @@ fn update_skill_cooldowns(
 
     for (child_of, mut text, mut visibility) in &mut overlays {
         if let Ok(slot) = skill_slots.get(child_of.parent()) {
-            if let Some(timer) = cooldowns.0.get(&slot.attack_id) {
+            if let Some(timer) = slot.attack_id.and_then(|attack_id| cooldowns.0.get(&attack_id)) {
                 if !timer.finished() {
                     let secs = timer.remaining_secs();
                     *text = Text::new(format!("{:.0}", secs));
@@ -291,61 +344,438 @@ fn inventory_controls(
     }
 }
 
+fn skill_slots_changed(
+    loadout_query: Query<Ref<SkillLoadout>, (With<PlayerData>, With<LocalPlayer>)>,
+    tree: Res<SkillTree>,
+    mut skill_slots: Query<&mut SkillSlot>,
+    mut names: Query<(&ChildOf, &mut Text), With<SkillNameText>>,
+) {
+    let Ok(loadout) = loadout_query.single() else { return };
+    if !loadout.is_changed() && !tree.is_changed() {
+        return;
+    }
+
+    for mut slot in skill_slots.iter_mut() {
+        slot.attack_id = loadout.0[slot.index];
+    }
+    for (child_of, mut text) in names.iter_mut() {
+        if let Ok(slot) = skill_slots.get(child_of.parent()) {
+            text.0 = slot.attack_id.and_then(|attack_id| tree.name_of(attack_id)).unwrap_or_default().to_string();
+        }
+    }
+}
+
+fn skill_tree_controls(
+    input: Res<ButtonInput<KeyCode>>,
+    mut ui_state: ResMut<SkillTreeUIState>,
+    mut panel_query: Query<&mut Node, With<SkillTreePanel>>,
+) {
+    if input.just_pressed(KeyCode::KeyK) {
+        ui_state.open = !ui_state.open;
+        for mut node in &mut panel_query {
+            node.display = if ui_state.open {
+                Display::Flex
+            } else {
+                Display::None
+            };
+        }
+    }
+}
+
+// One row per skill: its name and cost, then an unlock button or a button per skill key
+fn skill_tree_update(
+    mut commands: Commands,
+    ui_query: Query<(Entity, Option<&Children>), With<SkillTreePanel>>,
+    data_query: Query<(Ref<CharacterXp>, Ref<UnlockedSkills>, Ref<SkillLoadout>), (With<PlayerData>, With<LocalPlayer>)>,
+    tree: Res<SkillTree>,
+) {
+    let Ok((xp, unlocked, loadout)) = data_query.single() else { return };
+    if !xp.is_changed() && !unlocked.is_changed() && !loadout.is_changed() && !tree.is_changed() {
+        return;
+    }
+    let Ok((panel_entity, children)) = ui_query.single() else { return };
+
+    // The panel has no children until it is first filled
+    for child in children.iter().flat_map(|children| children.iter()) {
+        commands.entity(child).despawn();
+    }
+
+    commands.entity(panel_entity).with_children(|parent| {
+        parent.spawn(slot_text(format!("Skill points: {}", tree.points_available(&xp, &unlocked))));
+
+        for node in tree.nodes.iter() {
+            parent.spawn(Node {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(4.0),
+                ..default()
+            })
+            .with_children(|row| {
+                row.spawn(slot_text(format!("{} ({} pt, lvl {})", node.name, node.cost, node.min_level)));
+
+                if !unlocked.skills.contains(&node.attack) {
+                    let available = tree.can_unlock(node.attack, &xp, &unlocked).is_ok();
+                    row.spawn((
+                        Button,
+                        skill_button_node(),
+                        BorderRadius::all(BORDER_RADIUS),
+                        BackgroundColor(if available { Color::srgb(0.2, 0.5, 0.2) } else { Color::srgb(0.3, 0.3, 0.3) }),
+                        SkillUnlockButton(node.attack),
+                    ))
+                    .with_child(slot_text("+".to_string()));
+                    return;
+                }
+
+                for slot in 0..SKILL_SLOTS {
+                    let equipped = loadout.0[slot] == Some(node.attack);
+                    row.spawn((
+                        Button,
+                        skill_button_node(),
+                        BorderRadius::all(BORDER_RADIUS),
+                        BackgroundColor(if equipped { Color::srgb(0.6, 0.5, 0.1) } else { Color::srgb(0.3, 0.3, 0.3) }),
+                        SkillAssignButton { slot, attack_id: node.attack },
+                    ))
+                    .with_child(slot_text(format!("{}", slot + 1)));
+                }
+            });
+        }
+    });
+}
+
+fn skill_button_node() -> Node {
+    Node {
+        width: Val::Px(22.0),
+        height: Val::Px(22.0),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+// The server checks points, requirements and levels, the tree updates once it replicates the result
+fn skill_tree_buttons(
+    unlock_query: Query<(&Interaction, &SkillUnlockButton), Changed<Interaction>>,
+    assign_query: Query<(&Interaction, &SkillAssignButton), Changed<Interaction>>,
+    loadout_query: Query<&SkillLoadout, (With<PlayerData>, With<LocalPlayer>)>,
+    mut unlock_events: EventWriter<UnlockSkill>,
+    mut equip_events: EventWriter<EquipSkill>,
+) {
+    for (interaction, button) in unlock_query.iter() {
+        if *interaction == Interaction::Pressed {
+            unlock_events.write(UnlockSkill { attack: button.0 });
+        }
+    }
+
+    let Ok(loadout) = loadout_query.single() else { return };
+    for (interaction, button) in assign_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let attack = Some(button.attack_id).filter(|attack| loadout.0[button.slot] != Some(*attack));
+        equip_events.write(EquipSkill { slot: button.slot, attack });
+    }
+}
+
+// Trading is opened next to a merchant and closes when walking away
+fn shop_controls(
+    input: Res<ButtonInput<KeyCode>>,
+    mut ui_state: ResMut<ShopUIState>,
+    mut panel_query: Query<&mut Node, With<ShopPanel>>,
+    character: Query<&Position, (With<LocalPlayer>, With<Character>)>,
+    merchants: Query<&Merchant>,
+) {
+    let nearby = character.single().ok()
+        .and_then(|position| merchants.iter().find(|merchant| merchant.in_range(position.0)))
+        .map(|merchant| merchant.shop.clone());
+
+    let open = if input.just_pressed(KeyCode::KeyF) && ui_state.open.is_none() {
+        nearby
+    } else if input.just_pressed(KeyCode::KeyF) || nearby.is_none() {
+        None
+    } else {
+        ui_state.open.clone()
+    };
+
+    if open == ui_state.open {
+        return;
+    }
+    ui_state.open = open;
+    for mut node in &mut panel_query {
+        node.display = if ui_state.open.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+}
+
+// The merchant's stock, then everything in the inventory the merchant would buy
+fn shop_update(
+    mut commands: Commands,
+    ui_state: Res<ShopUIState>,
+    ui_query: Query<(Entity, Option<&Children>), With<ShopPanel>>,
+    data_query: Query<(Ref<Gold>, Ref<Inventory>), (With<PlayerData>, With<LocalPlayer>)>,
+    shops: Res<Shops>,
+    catalogue: Res<ItemCatalogue>,
+) {
+    let Ok((gold, inventory)) = data_query.single() else { return };
+    if !ui_state.is_changed() && !gold.is_changed() && !inventory.is_changed() && !shops.is_changed() && !catalogue.is_changed() {
+        return;
+    }
+    let Some(shop) = ui_state.open.as_ref().and_then(|name| shops.0.get(name)) else { return };
+    let Ok((panel_entity, children)) = ui_query.single() else { return };
+
+    for child in children.iter().flat_map(|children| children.iter()) {
+        commands.entity(child).despawn();
+    }
+
+    let item_name = |id: ItemId| catalogue.0.get(&id).map_or_else(|| id.to_string(), |spec| spec.name.clone());
+
+    commands.entity(panel_entity).with_children(|parent| {
+        parent.spawn(slot_text(format!("{} - {} Gold", shop.title, gold.value)));
+
+        for item in shop.stock.iter().copied() {
+            let Some(price) = shop.buy_price(item, &catalogue) else { continue };
+            let affordable = gold.value >= price as u128;
+            parent.spawn((
+                Button,
+                trade_row_node(),
+                BorderRadius::all(BORDER_RADIUS),
+                BackgroundColor(if affordable { Color::srgb(0.2, 0.4, 0.2) } else { Color::srgb(0.3, 0.3, 0.3) }),
+                ShopBuyButton(item),
+            ))
+            .with_child(slot_text(format!("Buy {} for {}", item_name(item), price)));
+        }
+
+        for (slot, stack) in inventory.slots.iter().enumerate() {
+            let Some(stack) = stack else { continue };
+            let Some(price) = shop.sell_price(stack.id, &catalogue) else { continue };
+            parent.spawn((
+                Button,
+                trade_row_node(),
+                BorderRadius::all(BORDER_RADIUS),
+                BackgroundColor(Color::srgb(0.4, 0.3, 0.2)),
+                ShopSellButton { slot, item: stack.id },
+            ))
+            .with_child(slot_text(format!("Sell {} ×{} for {}", item_name(stack.id), stack.qty, price)));
+        }
+    });
+}
+
+fn trade_row_node() -> Node {
+    Node {
+        height: Val::Px(22.0),
+        padding: UiRect::horizontal(Val::Px(4.0)),
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+// The server checks the gold, the stock and the distance to the merchant
+fn shop_buttons(
+    ui_state: Res<ShopUIState>,
+    buy_query: Query<(&Interaction, &ShopBuyButton), Changed<Interaction>>,
+    sell_query: Query<(&Interaction, &ShopSellButton), Changed<Interaction>>,
+    mut buy_events: EventWriter<BuyItem>,
+    mut sell_events: EventWriter<SellItem>,
+) {
+    let Some(shop) = ui_state.open.as_ref() else { return };
+
+    for (interaction, button) in buy_query.iter() {
+        if *interaction == Interaction::Pressed {
+            buy_events.write(BuyItem { shop: shop.clone(), item: button.0, qty: 1 });
+        }
+    }
+    for (interaction, button) in sell_query.iter() {
+        if *interaction == Interaction::Pressed {
+            sell_events.write(SellItem { shop: shop.clone(), slot: button.slot, item: button.item, qty: 1 });
+        }
+    }
+}
+
 fn inventory_update(
     mut ui_query: Query<(Entity, &mut Children), With<InventoryPanel>>,
     mut commands: Commands,
-    inventory_query: Query<&Inventory, Changed<Inventory>>,
+    inventory_query: Query<(Ref<Inventory>, Ref<Equipment>, Ref<Stash>), (With<PlayerData>, With<LocalPlayer>)>,
+    catalogue: Res<ItemCatalogue>,
 ) {
-    let Ok(inventory) = inventory_query.single() else { return };
+    let Ok((inventory, equipment, stash)) = inventory_query.single() else { return };
+    if !inventory.is_changed() && !equipment.is_changed() && !stash.is_changed() {
+        return;
+    }
     let Ok((panel_entity, children)) = ui_query.single_mut() else { return };
 
     for child in children.iter() {
         commands.entity(child).despawn();
     }
 
-    for slot in inventory.slots.iter() {
-        if let Some(stack) = slot {
-            commands.entity(panel_entity).with_children(|parent| {
-                parent.spawn((
-                    Node {
-                        width: Val::Px(30.0),
-                        height: Val::Px(30.0),
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    BorderRadius::all(BORDER_RADIUS),
-                    BackgroundColor(Color::srgb(0.3, 0.3, 0.3)),
-                ))
-                .with_children(|item| {
-                    item.spawn((
-                        Text::new(format!("{} ×{}", stack.id, stack.qty)),
-                        TextColor(Color::WHITE),
-                        TextFont {
-                            font_size: 14.0,
-                            ..default()
-                        },
-                    ));
-                });
+    let item_name = |id: ItemId| catalogue.0.get(&id).map_or_else(|| id.to_string(), |spec| spec.name.clone());
+
+    commands.entity(panel_entity).with_children(|parent| {
+        for slot in [EquipmentSlot::Weapon, EquipmentSlot::Armor, EquipmentSlot::Trinket] {
+            parent.spawn((
+                Button,
+                slot_node(),
+                BorderRadius::all(BORDER_RADIUS),
+                BackgroundColor(Color::srgb(0.4, 0.3, 0.2)),
+                EquipmentSlotButton(slot),
+            ))
+            .with_children(|item| {
+                let Some(id) = equipment.get(slot) else { return };
+                item.spawn(slot_text(item_name(id)));
+            });
+        }
+
+        for (index, slot) in inventory.slots.iter().enumerate() {
+            parent.spawn((
+                Button,
+                slot_node(),
+                BorderRadius::all(BORDER_RADIUS),
+                BackgroundColor(Color::srgb(0.3, 0.3, 0.3)),
+                InventorySlot(index),
+            ))
+            .with_children(|item| {
+                let Some(stack) = slot else { return };
+                item.spawn(slot_text(format!("{} ×{}", item_name(stack.id), stack.qty)));
+            });
+        }
+
+        // Overflow waiting for room, the server moves it into the inventory by itself
+        if !stash.items.is_empty() {
+            parent.spawn((
+                Node { width: Val::Percent(100.0), ..default() },
+                slot_text("Stash".to_string()),
+            ));
+        }
+        for stack in stash.items.iter() {
+            parent.spawn((
+                slot_node(),
+                BorderRadius::all(BORDER_RADIUS),
+                BackgroundColor(Color::srgb(0.2, 0.2, 0.25)),
+            ))
+            .with_children(|item| {
+                item.spawn(slot_text(format!("{} ×{}", item_name(stack.id), stack.qty)));
             });
         }
+    });
+}
+
+fn slot_node() -> Node {
+    Node {
+        width: Val::Px(30.0),
+        height: Val::Px(30.0),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+fn slot_text(text: String) -> impl Bundle {
+    (
+        Text::new(text),
+        TextColor(Color::WHITE),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+    )
+}
+
+// Clicking a slot or pressing its hotkey asks the server to use the item in it
+fn use_item_controls(
+    input: Res<ButtonInput<KeyCode>>,
+    slot_query: Query<(&Interaction, &InventorySlot), Changed<Interaction>>,
+    inventory_query: Query<&Inventory, (With<PlayerData>, With<LocalPlayer>)>,
+    mut use_events: EventWriter<UseItem>,
+) {
+    let Ok(inventory) = inventory_query.single() else { return };
+
+    let clicked = slot_query.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, slot)| slot.0);
+    let pressed = ITEM_HOTKEYS.iter()
+        .enumerate()
+        .filter(|(_, key)| input.just_pressed(**key))
+        .map(|(slot, _)| slot);
+
+    for slot in clicked.chain(pressed) {
+        if let Some(Some(stack)) = inventory.slots.get(slot) {
+            use_events.write(UseItem { slot, item: stack.id });
+        }
+    }
+}
+
+fn unequip_controls(
+    slot_query: Query<(&Interaction, &EquipmentSlotButton), Changed<Interaction>>,
+    mut unequip_events: EventWriter<UnequipItem>,
+) {
+    for (interaction, button) in slot_query.iter() {
+        if *interaction == Interaction::Pressed {
+            unequip_events.write(UnequipItem { slot: button.0 });
+        }
     }
 }
 
 fn xp_changed(
     mut xp_ui_query: Query<&mut Node, With<XPBar>>,
-    xp_query: Query<&CharacterXp, Changed<CharacterXp>>,
+    mut level_text_query: Query<&mut Text, With<LevelText>>,
+    xp_query: Query<&CharacterXp, (With<PlayerData>, With<LocalPlayer>, Changed<CharacterXp>)>,
+    curve: Res<XpCurve>,
 ) {
     let Ok(xp) = xp_query.single() else { return };
     let Ok(mut node) = xp_ui_query.single_mut() else { return };
 
-    let progress = xp.value as f32 / 5 as f32;
+    let progress = xp.value as f32 / curve.xp_to_next(xp.level) as f32;
     node.width = Val::Px(XP_BAR_WIDTH * progress.clamp(0.0, 1.0));
+
+    if let Ok(mut text) = level_text_query.single_mut() {
+        text.0 = xp.level.to_string();
+    }
+}
+
+fn level_up_toast(
+    mut commands: Commands,
+    mut level_ups: EventReader<LevelUp>,
+) {
+    for level_up in level_ups.read() {
+        commands.spawn((
+            Text::new(format!("Level {}!", level_up.level)),
+            TextColor(Color::srgb(1.0, 0.875, 0.0)),
+            TextFont {
+                font_size: BASE_FONT_SIZE * 2.5,
+                ..default()
+            },
+            TextLayout::new_with_justify(JustifyText::Center),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(20.0),
+                width: Val::Percent(100.0),
+                ..default()
+            },
+            Toast { timer: Timer::from_seconds(TOAST_SECONDS, TimerMode::Once) },
+        ));
+    }
+}
+
+fn fade_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut Toast, &mut TextColor)>,
+) {
+    for (entity, mut toast, mut color) in toasts.iter_mut() {
+        toast.timer.tick(time.delta());
+        if toast.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        // Fully visible for the first half, then fades out
+        color.0.set_alpha((2.0 * (1.0 - toast.timer.fraction())).min(1.0));
+    }
 }
 
 fn gold_changed(
     mut ui_query: Query<&mut Text, With<GoldText>>,
-    gold_query: Query<&Gold, Changed<Gold>>,
+    gold_query: Query<&Gold, (With<PlayerData>, With<LocalPlayer>, Changed<Gold>)>,
 ) {
     let Ok(gold) = gold_query.single() else { return };
     let Ok(mut text) = ui_query.single_mut() else { return };
@@ -364,3 +794,29 @@ fn character_health_changed(
     text.0 = value.to_string() + " HP";
 }
 
+fn show_connection_error(
+    mut commands: Commands,
+    error: Res<ConnectionError>,
+) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            Text::new(error.0.clone()),
+            TextColor(Color::srgb(1.0, 0.4, 0.4)),
+            TextFont {
+                font_size: BASE_FONT_SIZE * 1.5,
+                ..default()
+            },
+        ));
+    });
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::components::character::Character;
+use crate::components::island::OnIsland;
+use crate::components::overworld::Ship;
+use crate::components::player::PlayerData;
+use crate::plugins::network::OwnedBy;
+use crate::preludes::network_preludes::*;
+
+/// Server side index of which clients have a character on which island.
+/// Island events are sent to these clients only, the rest cannot see the entities involved.
+#[derive(Resource, Default)]
+pub struct IslandOccupants(HashMap<u64, Vec<Entity>>);
+
+impl IslandOccupants {
+    pub fn clients(&self, island: u64) -> &[Entity] {
+        self.0.get(&island).map_or(&[], |clients| clients.as_slice())
+    }
+
+    pub fn contains(&self, island: u64, client: Entity) -> bool {
+        self.clients(island).contains(&client)
+    }
+
+    /// Occupants plus the server itself, for events the server has to simulate as well.
+    pub fn with_server(&self, island: u64) -> impl Iterator<Item = Entity> + '_ {
+        let server = (!self.contains(island, SERVER)).then_some(SERVER);
+        self.clients(island).iter().copied().chain(server)
+    }
+}
+
+pub struct VisibilityPlugin;
+impl Plugin for VisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<IslandOccupants>()
+        .add_systems(First, update_island_occupants.run_if(server_running))
+        .add_systems(PostUpdate, update_client_visibility.before(ServerSet::Send).run_if(server_running));
+    }
+}
+
+fn update_island_occupants(
+    mut occupants: ResMut<IslandOccupants>,
+    characters: Query<(&OwnedBy, &OnIsland), With<Character>>,
+) {
+    occupants.0.clear();
+    for (owner, island) in characters.iter() {
+        occupants.0.entry(island.0).or_default().push(owner.0);
+    }
+}
+
+// Clients only receive the entities on their own island, their own ship and their own items
+fn update_client_visibility(
+    mut clients: Query<(Entity, &mut ClientVisibility), With<ConnectedClient>>,
+    characters: Query<(&OwnedBy, &OnIsland), With<Character>>,
+    island_entities: Query<(Entity, &OnIsland), With<Replicated>>,
+    ships: Query<(Entity, &OwnedBy), With<Ship>>,
+    player_data: Query<(Entity, &OwnedBy), With<PlayerData>>,
+) {
+    for (client_entity, mut visibility) in clients.iter_mut() {
+        let island = characters.iter()
+            .find(|(owner, _)| owner.0 == client_entity)
+            .map(|(_, island)| island.0);
+
+        for (entity, on_island) in island_entities.iter() {
+            visibility.set_visibility(entity, island == Some(on_island.0));
+        }
+
+        for (entity, owner) in ships.iter().chain(player_data.iter()) {
+            visibility.set_visibility(entity, owner.0 == client_entity);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::components::character::Character;
+use crate::components::island::{ChestTier, CompletedIslandObjective};
+use crate::components::island_maps::{Map, TerrainChange, TerrainType};
+use crate::components::overworld::Island;
+use crate::components::player::{CharacterXp, Equipment, Gold, Inventory, PlayerData, SaveEvent, SkillLoadout, Stash, UnlockedSkills};
+use crate::plugins::network::{OwnedBy, PlayerId};
+use crate::plugins::profile::{write_atomically, PlayerDataQuery, SaveThrottle, SavedProfile};
+use crate::preludes::network_preludes::*;
+
+/// Bump whenever [`SavedWorld`] changes shape.
+pub const WORLD_VERSION: u32 = 2;
+
+/// Progress on one island that generation from the seed cannot reproduce.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct IslandState {
+    pub completed: bool,
+    /// Reward chest waiting on the island, cleared again once it is looted
+    pub chest: Option<IVec3>,
+    pub chest_tier: ChestTier,
+    /// Terrain changes on top of the generated map, oldest first
+    pub terrain: Vec<(IVec3, Option<TerrainType>)>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+struct SavedWorld {
+    version: u32,
+    seed: u64,
+    islands: HashMap<u64, IslandState>,
+    players: HashMap<u64, SavedProfile>,
+}
+
+/// Server side world progress. Always kept in memory, written to disk when the server was given a world path.
+#[derive(Resource, Default)]
+pub struct WorldState {
+    path: Option<PathBuf>,
+    pub seed: u64,
+    pub islands: HashMap<u64, IslandState>,
+    /// Progress of every client that joined, keyed by their netcode client id
+    pub players: HashMap<u64, SavedProfile>,
+}
+
+impl WorldState {
+    /// Opens the world at `path`, a new world with `seed` is started when the file does not exist yet.
+    pub fn open(path: &Path, seed: u64) -> Result<Self, Box<dyn Error>> {
+        let saved = match fs::read_to_string(path) {
+            Ok(contents) => ron::from_str::<SavedWorld>(&contents)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                info!("Starting a new world at {}", path.display());
+                let world = Self { path: Some(path.to_path_buf()), seed, ..default() };
+                world.save()?;
+                return Ok(world);
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if saved.version > WORLD_VERSION {
+            return Err(format!("{} was saved by a newer version ({} > {WORLD_VERSION})", path.display(), saved.version).into());
+        }
+
+        Ok(Self { path: Some(path.to_path_buf()), seed: saved.seed, islands: saved.islands, players: saved.players })
+    }
+
+    pub fn island(&self, island: u64) -> Option<&IslandState> {
+        self.islands.get(&island)
+    }
+
+    pub fn island_mut(&mut self, island: u64) -> &mut IslandState {
+        self.islands.entry(island).or_default()
+    }
+
+    /// Replays the saved terrain changes on a freshly generated map.
+    pub fn restore_terrain(&self, island: u64, map: &mut Map) {
+        if let Some(state) = self.island(island) {
+            for (position, terrain) in state.terrain.iter() {
+                map.set_terrain(*position, *terrain);
+            }
+        }
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let saved = SavedWorld { version: WORLD_VERSION, seed: self.seed, islands: self.islands.clone(), players: self.players.clone() };
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        write_atomically(path, &ron::ser::to_string_pretty(&saved, ron::ser::PrettyConfig::default())?)?;
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default)]
+struct WorldSaves(SaveThrottle);
+
+pub struct WorldStatePlugin;
+impl Plugin for WorldStatePlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<WorldState>()
+        .init_resource::<WorldSaves>()
+        .add_observer(save_world_on_request)
+        .add_systems(Update, (restore_completed_islands, record_terrain_changes, record_player_progress, save_world_state).chain().run_if(server_running));
+    }
+}
+
+fn restore_completed_islands(
+    mut commands: Commands,
+    world: Res<WorldState>,
+    islands: Query<(Entity, &Island), Added<Island>>,
+) {
+    for (entity, island) in islands.iter() {
+        if world.island(island.0).is_some_and(|state| state.completed) {
+            commands.entity(entity).insert(CompletedIslandObjective);
+        }
+    }
+}
+
+fn record_terrain_changes(
+    mut changes: EventReader<TerrainChange>,
+    mut world: ResMut<WorldState>,
+) {
+    for change in changes.read() {
+        let terrain = &mut world.island_mut(change.island).terrain;
+        terrain.retain(|(position, _)| *position != change.position);
+        terrain.push((change.position, change.terrain));
+    }
+}
+
+fn record_player_progress(
+    mut world: ResMut<WorldState>,
+    player_ids: Query<&PlayerId>,
+    data: Query<(&OwnedBy, PlayerDataQuery), (With<PlayerData>, Or<(Changed<OwnedBy>, Changed<CharacterXp>, Changed<Gold>, Changed<Inventory>, Changed<Stash>, Changed<Equipment>, Changed<UnlockedSkills>, Changed<SkillLoadout>)>)>,
+) {
+    for (owner, data) in data.iter() {
+        // The host's own progress goes to its local profile instead
+        let Ok(player_id) = player_ids.get(owner.0) else {
+            continue;
+        };
+
+        let overworld_position = world.players.get(&player_id.0).and_then(|saved| saved.overworld_position);
+        world.players.insert(player_id.0, SavedProfile::from_player_data(data, overworld_position));
+    }
+}
+
+// Changes are written every few seconds, and right away when a character leaves an island or the server
+fn save_world_state(
+    time: Res<Time>,
+    world: Res<WorldState>,
+    mut saves: ResMut<WorldSaves>,
+    mut removed_characters: RemovedComponents<Character>,
+) {
+    if world.is_changed() && !world.is_added() {
+        saves.0.mark_dirty();
+    }
+
+    let character_left = removed_characters.read().count() > 0;
+    if saves.0.tick(time.delta()) || (character_left && saves.0.take()) {
+        save_world(&world);
+    }
+}
+
+fn save_world_on_request(
+    _trigger: Trigger<SaveEvent>,
+    world: Res<WorldState>,
+    mut saves: ResMut<WorldSaves>,
+) {
+    if saves.0.take() {
+        save_world(&world);
+    }
+}
+
+fn save_world(world: &WorldState) {
+    if let Err(error) = world.save() {
+        error!("Could not save the world: {error}");
+    }
+}
@@ -1,4 +1,5 @@
 pub use bevy_replicon::prelude::*;
+pub use bevy_replicon::{client::ServerUpdateTick, server::server_tick::ServerTick};
 pub use std::collections::HashSet;
 
 pub use bevy_replicon_renet2::{
@@ -14,7 +15,7 @@ pub use bevy_replicon_renet2::{
 
 pub use std::{
     error::Error,
-    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
     time::SystemTime,
 };
 
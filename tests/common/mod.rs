@@ -0,0 +1,189 @@
+//! In-process multiplayer harness: one server `App` and any number of client `App`s built from
+//! the simulation plugins, exchanging replicon messages in memory instead of over sockets.
+
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use clap::Parser;
+use bevy_replicon::prelude::{ConnectedClient, NetworkId};
+use bevy_replicon::shared::server_entity_map::ServerEntityMap;
+use bevy_replicon::test_app::ServerTestAppExt;
+
+use dice_venture::components::character::{Character, LocalPlayer};
+use dice_venture::components::humanoid::{MoveDirection, PositionUpdate};
+use dice_venture::components::island::{EnteredIsland, GenerateIsland};
+use dice_venture::components::overworld::{Island, LocalIsland, Ship};
+use dice_venture::plugins::attack::{AttackEvent, AttackId};
+use dice_venture::plugins::network::{Cli, OwnedBy, PlayerId, Transport};
+use dice_venture::{GameState, SimulationPlugin, SERVER_TICK_RATE};
+
+/// Upper bound for [`TestNetwork::run_until`], a few seconds of simulated time.
+pub const MAX_STEPS: usize = 600;
+
+pub struct TestClient {
+    pub app: App,
+    pub player_id: u64,
+    next_sequence: u32,
+}
+
+pub struct TestNetwork {
+    pub server: App,
+    pub clients: Vec<TestClient>,
+}
+
+/// Builds a peer for `args`, a dedicated server or a client, connected in-process instead of over a socket.
+fn build_app(args: &[&str]) -> App {
+    let mut app = App::new();
+    app.insert_resource(Cli::parse_from(std::iter::once("dice_venture").chain(args.iter().copied())))
+        .add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin, SimulationPlugin { transport: Transport::InMemory }))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / SERVER_TICK_RATE)));
+
+    app.finish();
+    app.cleanup();
+    app
+}
+
+impl TestNetwork {
+    /// Starts a server and connects `client_count` clients, player ids start at 1.
+    pub fn new(client_count: usize) -> Self {
+        let mut server = build_app(&["dedicated"]);
+        server.update();
+
+        let mut clients = Vec::new();
+        for index in 0..client_count {
+            let player_id = index as u64 + 1;
+            let mut app = build_app(&["client"]);
+            app.update();
+            server.connect_client(&mut app);
+            // Sockets get the id from netcode, here the player id stands in for it
+            let world = server.world_mut();
+            let client_entity = world.query_filtered::<Entity, (With<ConnectedClient>, Without<NetworkId>)>()
+                .single(world)
+                .expect("the new client should be connected");
+            world.entity_mut(client_entity).insert(NetworkId::new(player_id));
+            clients.push(TestClient { app, player_id, next_sequence: 0 });
+        }
+
+        let mut network = Self { server, clients };
+        // The overworld state arrives with the game info, the client's own ship may need a few more frames
+        let joined = network.run_until(|network| {
+            (0..network.clients.len()).all(|client| {
+                let in_overworld = *network.client_state(client) == GameState::Overworld;
+                let world = network.client(client).world_mut();
+                in_overworld && world.query_filtered::<(), (With<Ship>, With<LocalPlayer>)>().iter(world).count() == 1
+            })
+        });
+        assert!(joined, "clients did not reach the overworld");
+
+        network
+    }
+
+    /// Advances every peer by one frame and delivers the messages sent in between.
+    pub fn step(&mut self) {
+        for client in self.clients.iter_mut() {
+            client.app.update();
+            self.server.exchange_with_client(&mut client.app);
+        }
+
+        self.server.update();
+
+        for client in self.clients.iter_mut() {
+            self.server.exchange_with_client(&mut client.app);
+        }
+    }
+
+    pub fn run(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    /// Steps until `condition` holds, returns false when [`MAX_STEPS`] ran out first.
+    pub fn run_until(&mut self, mut condition: impl FnMut(&mut Self) -> bool) -> bool {
+        for _ in 0..MAX_STEPS {
+            if condition(self) {
+                return true;
+            }
+            self.step();
+        }
+        condition(self)
+    }
+
+    pub fn client(&mut self, client: usize) -> &mut App {
+        &mut self.clients[client].app
+    }
+
+    pub fn client_state(&self, client: usize) -> &GameState {
+        self.clients[client].app.world().resource::<State<GameState>>().get()
+    }
+
+    /// Ids of the islands the client generated from the world seed, in a stable order.
+    pub fn island_ids(&mut self, client: usize) -> Vec<u64> {
+        let world = self.client(client).world_mut();
+        let mut ids: Vec<u64> = world.query::<&Island>().iter(world).map(|island| island.0).collect();
+        ids.sort();
+        ids
+    }
+
+    /// Does what clicking an island does on a real client, minus the visuals.
+    pub fn enter_island(&mut self, client: usize, island_id: u64) {
+        let world = self.client(client).world_mut();
+        let island_entity = world.query::<(Entity, &Island)>()
+            .iter(world)
+            .find(|(_, island)| island.0 == island_id)
+            .map(|(entity, _)| entity)
+            .expect("island should exist on the client");
+
+        world.entity_mut(island_entity).insert((LocalIsland, GenerateIsland));
+        world.send_event(EnteredIsland(island_id));
+        world.resource_mut::<NextState<GameState>>().set(GameState::Island);
+    }
+
+    /// Sends a movement input the way `movement_input` does, without prediction.
+    pub fn send_move(&mut self, client: usize, direction: IVec3) {
+        let test_client = &mut self.clients[client];
+        test_client.next_sequence += 1;
+        let sequence = test_client.next_sequence;
+        test_client.app.world_mut().send_event(MoveDirection { direction, sequence });
+    }
+
+    pub fn attack(&mut self, client: usize, attack_id: AttackId, offset: IVec3) {
+        let character = self.local_character(client).expect("client should own a character");
+        self.client(client).world_mut().trigger(AttackEvent::new(character, attack_id, offset));
+    }
+
+    /// The character the client controls, as seen by that client.
+    pub fn local_character(&mut self, client: usize) -> Option<Entity> {
+        let world = self.client(client).world_mut();
+        world.query_filtered::<Entity, (With<Character>, With<LocalPlayer>)>().iter(world).next()
+    }
+
+    /// The character of a client, as seen by the server.
+    pub fn server_character(&mut self, client: usize) -> Option<Entity> {
+        let player_id = self.clients[client].player_id;
+        let world = self.server.world_mut();
+        let client_entity = world.query::<(Entity, &PlayerId)>()
+            .iter(world)
+            .find(|(_, id)| id.0 == player_id)
+            .map(|(entity, _)| entity)?;
+
+        world.query_filtered::<(Entity, &OwnedBy), With<Character>>()
+            .iter(world)
+            .find(|(_, owner)| owner.0 == client_entity)
+            .map(|(entity, _)| entity)
+    }
+
+    /// Moves an entity on the server through the regular position update path.
+    pub fn server_teleport(&mut self, entity: Entity, position: IVec3) {
+        self.server.world_mut().send_event(PositionUpdate { new_position: position, entity });
+    }
+}
+
+/// Replicated entities are mapped to different ids on each peer, this finds a client's copy.
+pub fn client_entity_of(network: &mut TestNetwork, client: usize, server_entity: Entity) -> Option<Entity> {
+    network.client(client).world().resource::<ServerEntityMap>().to_client().get(&server_entity).copied()
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::{client_entity_of, TestNetwork};
+use dice_venture::attacks::base_attack::BaseAttack;
+use dice_venture::components::character::{Character, LocalPlayer};
+use dice_venture::components::enemy::{Attacks, Enemy, EnemyState, RangeAggro};
+use dice_venture::components::humanoid::{Health, Position, RemoveEntity};
+use dice_venture::components::island::OnIsland;
+use dice_venture::components::island_maps::{IslandMaps, TileType};
+use dice_venture::components::overworld::Ship;
+use dice_venture::plugins::attack::key_of;
+use dice_venture::plugins::shop::Merchant;
+use dice_venture::GameState;
+
+const DIRECTIONS: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+fn position(app: &App, entity: Entity) -> Option<IVec3> {
+    app.world().get::<Position>(entity).map(|position| position.0)
+}
+
+/// Enters the first island with client 0 and waits until both sides agree on its character.
+fn enter_first_island(network: &mut TestNetwork, client: usize) -> (u64, Entity, Entity) {
+    let island_id = network.island_ids(client)[0];
+    network.enter_island(client, island_id);
+
+    let arrived = network.run_until(|network| {
+        let (Some(server_character), Some(client_character)) = (network.server_character(client), network.local_character(client)) else {
+            return false;
+        };
+        // Characters start at the default position until the server has placed them on the map
+        let Some(server_position) = position(&network.server, server_character) else {
+            return false;
+        };
+        let placed = network.server.world().resource::<IslandMaps>().get_map(island_id)
+            .is_some_and(|map| map.get_tile(server_position).entity == server_character);
+        placed && position(network.client(client), client_character) == Some(server_position)
+    });
+    assert!(arrived, "character did not arrive on the island");
+
+    (island_id, network.server_character(client).unwrap(), network.local_character(client).unwrap())
+}
+
+#[test]
+fn clients_join_with_their_own_ship() {
+    let mut network = TestNetwork::new(2);
+
+    let world = network.server.world_mut();
+    assert_eq!(world.query_filtered::<(), With<Ship>>().iter(world).count(), 2);
+
+    for client in 0..2 {
+        let world = network.client(client).world_mut();
+        assert_eq!(world.query_filtered::<(), (With<Ship>, With<LocalPlayer>)>().iter(world).count(), 1);
+    }
+}
+
+#[test]
+fn entering_an_island_places_the_character_on_both_maps() {
+    let mut network = TestNetwork::new(1);
+    let (island_id, server_character, client_character) = enter_first_island(&mut network, 0);
+
+    assert_eq!(*network.client_state(0), GameState::Island);
+    assert_eq!(network.client(0).world().get::<OnIsland>(client_character).map(|island| island.0), Some(island_id));
+
+    let server_position = position(&network.server, server_character).unwrap();
+    let server_map = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap();
+    assert_eq!(server_map.player_count, 1);
+    assert_eq!(server_map.get_tile(server_position).kind, TileType::Player);
+    assert_eq!(server_map.get_tile(server_position).entity, server_character);
+
+    let client_map = network.client(0).world().resource::<IslandMaps>().get_map(island_id).unwrap();
+    assert_eq!(client_map.get_tile(server_position).kind, TileType::Player);
+    assert_eq!(client_map.get_tile(server_position).entity, client_character);
+}
+
+#[test]
+fn movement_is_applied_by_the_server_and_replicated() {
+    let mut network = TestNetwork::new(2);
+    let (island_id, server_character, client_character) = enter_first_island(&mut network, 0);
+
+    let start = position(&network.server, server_character).unwrap();
+    let (direction, target) = {
+        let map = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap();
+        DIRECTIONS.iter()
+            .find_map(|direction| map.step_target(start, *direction).map(|target| (*direction, target)))
+            .expect("character should be able to step somewhere")
+    };
+
+    network.send_move(0, direction);
+    let moved = network.run_until(|network| {
+        position(&network.server, server_character) == Some(target)
+            && position(network.client(0), client_character) == Some(target)
+    });
+    assert!(moved, "move was not applied on both peers");
+
+    let server_map = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap();
+    assert_eq!(server_map.get_tile(target).entity, server_character);
+    assert_ne!(server_map.get_tile(start).kind, TileType::Player);
+
+    let client_map = network.client(0).world().resource::<IslandMaps>().get_map(island_id).unwrap();
+    assert_eq!(client_map.get_tile(target).entity, client_character);
+    assert_ne!(client_map.get_tile(start).kind, TileType::Player);
+}
+
+#[test]
+fn steps_arriving_in_one_frame_build_on_each_other() {
+    let mut network = TestNetwork::new(1);
+    let (island_id, server_character, _) = enter_first_island(&mut network, 0);
+
+    let start = position(&network.server, server_character).unwrap();
+    let (direction, target) = {
+        let map = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap();
+        DIRECTIONS.iter()
+            .find_map(|direction| {
+                let first = map.step_target(start, *direction)?;
+                map.step_target(first, *direction).map(|second| (*direction, second))
+            })
+            .expect("character should be able to take two steps somewhere")
+    };
+
+    network.send_move(0, direction);
+    network.send_move(0, direction);
+    let moved = network.run_until(|network| position(&network.server, server_character) == Some(target));
+    assert!(moved, "second step was checked against the position before the first");
+}
+
+#[test]
+fn vertical_steps_are_rejected() {
+    let mut network = TestNetwork::new(1);
+    let (_, server_character, client_character) = enter_first_island(&mut network, 0);
+
+    let start = position(&network.server, server_character).unwrap();
+    network.send_move(0, IVec3::Y);
+    network.run(30);
+
+    assert_eq!(position(&network.server, server_character), Some(start));
+    assert_eq!(position(network.client(0), client_character), Some(start));
+}
+
+#[test]
+fn second_client_sees_the_first_character() {
+    let mut network = TestNetwork::new(2);
+    let (island_id, server_character, _) = enter_first_island(&mut network, 0);
+
+    network.enter_island(1, island_id);
+    let seen = network.run_until(|network| {
+        let Some(remote) = client_entity_of(network, 1, server_character) else {
+            return false;
+        };
+        position(network.client(1), remote) == position(&network.server, server_character)
+    });
+    assert!(seen, "second client never saw the first character at its server position");
+
+    let remote = client_entity_of(&mut network, 1, server_character).unwrap();
+    let world = network.client(1).world();
+    assert!(world.get::<Character>(remote).is_some());
+    assert!(world.get::<LocalPlayer>(remote).is_none());
+    assert_eq!(world.get::<OnIsland>(remote).map(|island| island.0), Some(island_id));
+}
+
+#[test]
+fn attacking_an_enemy_damages_it_on_every_peer() {
+    let mut network = TestNetwork::new(1);
+    let (island_id, server_character, _) = enter_first_island(&mut network, 0);
+
+    let world = network.server.world_mut();
+    let enemy = world.query_filtered::<(Entity, &OnIsland), With<Enemy>>()
+        .iter(world)
+        .find(|(_, island)| island.0 == island_id)
+        .map(|(entity, _)| entity)
+        .expect("island should have enemies");
+
+    // Turn the enemy into a training dummy so it stays where we put it
+    world.entity_mut(enemy).remove::<(RangeAggro, Attacks)>().insert(EnemyState::Idle);
+
+    // Characters arrive above the harbour, look for open ground with room for the enemy next to it
+    let (player_position, direction) = {
+        let map = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap();
+        let leave_tile = map.leave_position + IVec3::Y;
+        let free = |tile: IVec3| tile != leave_tile && map.can_move(tile) && map.get_tile(tile).kind == TileType::Empty;
+        map.above_water_top_tiles().into_iter()
+            .map(|top| top + IVec3::Y)
+            .filter(|tile| free(*tile))
+            .find_map(|tile| DIRECTIONS.iter().find(|direction| free(tile + **direction)).map(|direction| (tile, *direction)))
+            .expect("island should have two free neighbouring tiles")
+    };
+
+    network.server_teleport(server_character, player_position);
+    let moved = network.run_until(|network| {
+        let client_character = network.local_character(0).unwrap();
+        position(&network.server, server_character) == Some(player_position)
+            && position(network.client(0), client_character) == Some(player_position)
+    });
+    assert!(moved, "character was not moved onto open ground");
+
+    network.server_teleport(enemy, player_position + direction);
+    let placed = network.run_until(|network| position(&network.server, enemy) == Some(player_position + direction));
+    assert!(placed, "enemy was not moved next to the character");
+
+    let max_health = network.server.world().get::<Health>(enemy).unwrap().max;
+    network.attack(0, key_of::<BaseAttack>(), direction);
+
+    let damaged = network.run_until(|network| {
+        let Some(client_enemy) = client_entity_of(network, 0, enemy) else {
+            return false;
+        };
+        let server_health = network.server.world().get::<Health>(enemy).map(|health| health.value);
+        let client_health = network.client(0).world().get::<Health>(client_enemy).map(|health| health.value);
+        server_health.is_some_and(|health| health < max_health) && server_health == client_health
+    });
+    assert!(damaged, "enemy health did not drop on both peers");
+}
+
+#[test]
+fn leaving_the_island_returns_to_the_overworld() {
+    let mut network = TestNetwork::new(1);
+    let (island_id, server_character, _) = enter_first_island(&mut network, 0);
+
+    let leave_position = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap().leave_position;
+    network.server_teleport(server_character, leave_position + IVec3::Y);
+
+    let left = network.run_until(|network| {
+        *network.client_state(0) == GameState::Overworld && network.server_character(0).is_none()
+    });
+    assert!(left, "character did not leave the island");
+
+    let server_map = network.server.world().resource::<IslandMaps>().get_map(island_id);
+    assert!(server_map.is_none_or(|map| map.player_count == 0));
+    assert!(network.client(0).world().resource::<IslandMaps>().get_map(island_id).is_none());
+    assert!(network.local_character(0).is_none());
+}
+
+#[test]
+fn an_island_with_a_merchant_unloads_once_the_players_leave() {
+    let mut network = TestNetwork::new(1);
+    let (island_id, server_character, _) = enter_first_island(&mut network, 0);
+
+    let leave_position = network.server.world().resource::<IslandMaps>().get_map(island_id).unwrap().leave_position;
+    let merchant_position = leave_position + IVec3::new(2, 1, 0);
+    let merchant = network.server.world_mut().spawn((
+        Merchant { shop: "test".to_string(), position: merchant_position },
+        Position::new(merchant_position),
+        OnIsland(island_id),
+    )).id();
+
+    network.server_teleport(server_character, leave_position + IVec3::Y);
+
+    // The merchant does not count as a player, so the island is cleaned up like any other
+    let unloaded = network.run_until(|network| {
+        network.server.world().resource::<IslandMaps>().get_map(island_id).is_none()
+    });
+    assert!(unloaded, "island stayed loaded with only a merchant on it");
+
+    let merchant = network.server.world().get_entity(merchant).ok();
+    assert!(merchant.is_none_or(|merchant| merchant.contains::<RemoveEntity>()), "merchant was not removed with the island");
+}